@@ -1,5 +1,5 @@
 use argparse::{ArgumentParser, Store};
-use fcm::{Client, MessageBuilder};
+use fcm::{Client, DataPayload, Message, ServiceAccountKey, ToFcmData};
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -7,30 +7,40 @@ struct CustomData {
     message: &'static str,
 }
 
+impl ToFcmData for CustomData {
+    fn to_fcm_data(&self) -> Result<DataPayload, fcm::Error> {
+        DataPayload::from_serialize(self)
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     pretty_env_logger::init();
 
     let mut device_token = String::new();
-    let mut api_key = String::new();
+    let mut project_id = String::new();
+    let mut key_file = String::new();
 
     {
         let mut ap = ArgumentParser::new();
         ap.set_description("A simple FCM notification sender");
         ap.refer(&mut device_token)
             .add_option(&["-t", "--device_token"], Store, "Device token");
-        ap.refer(&mut api_key)
-            .add_option(&["-k", "--api_key"], Store, "API key");
+        ap.refer(&mut project_id)
+            .add_option(&["-p", "--project_id"], Store, "Firebase project id");
+        ap.refer(&mut key_file)
+            .add_option(&["-k", "--key_file"], Store, "Service account key file");
         ap.parse_args_or_exit();
     }
 
-    let client = Client::new();
+    let key = ServiceAccountKey::from_file(&key_file)?;
+    let client = Client::with_service_account(project_id, key)?;
     let data = CustomData { message: "howdy" };
 
-    let mut builder = MessageBuilder::new(&api_key, &device_token);
+    let mut builder = Message::to_token(&device_token);
     builder.data(&data)?;
 
-    let response = client.send(builder.finalize()).await?;
+    let response = client.send(builder.finalize()?).await?;
     println!("Sent: {:?}", response);
 
     Ok(())
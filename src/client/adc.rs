@@ -0,0 +1,222 @@
+use std::{env, fs, path::PathBuf};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::client::oauth::{AuthorizedUserCredentials, ServiceAccountKey};
+use crate::client::provider::{MetadataServerProvider, TokenProvider, METADATA_HOST_ENV};
+use crate::client::response::FcmError;
+use crate::client::token::AccessToken;
+
+/// Points to a credential file, checked first.
+const CREDENTIALS_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+
+/// Names the project when the credentials don't.
+const PROJECT_ENV: &str = "GOOGLE_CLOUD_PROJECT";
+
+const WELL_KNOWN_FILE: &str = "application_default_credentials.json";
+
+/// Credentials found the way the Google Cloud client libraries find them.
+///
+/// In order, these are tried:
+///
+/// 1. The credential file named by `GOOGLE_APPLICATION_CREDENTIALS`.
+/// 2. The file written by `gcloud auth application-default login`.
+/// 3. The service account of the instance, through the metadata server.
+///
+/// The project id is read from `GOOGLE_CLOUD_PROJECT`, or else from the
+/// credentials themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplicationDefaultCredentials {
+    source: CredentialSource,
+    project_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CredentialSource {
+    ServiceAccount(ServiceAccountKey),
+    AuthorizedUser(AuthorizedUserCredentials),
+    MetadataServer(MetadataServerProvider),
+}
+
+impl ApplicationDefaultCredentials {
+    /// Look for credentials in the environment of the process.
+    pub fn discover() -> Result<ApplicationDefaultCredentials, FcmError> {
+        Self::discover_with(|name| env::var(name).ok())
+    }
+
+    fn discover_with<F>(var: F) -> Result<ApplicationDefaultCredentials, FcmError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let source = match var(CREDENTIALS_ENV).filter(|path| !path.is_empty()) {
+            Some(path) => Self::from_file(PathBuf::from(path))?,
+            None => match well_known_file(&var).filter(|path| path.is_file()) {
+                Some(path) => Self::from_file(path)?,
+                None => CredentialSource::MetadataServer(match var(METADATA_HOST_ENV) {
+                    Some(host) => MetadataServerProvider::new().with_host(format!("http://{}", host)),
+                    None => MetadataServerProvider::new(),
+                }),
+            },
+        };
+
+        Ok(ApplicationDefaultCredentials {
+            source,
+            project_id: var(PROJECT_ENV).filter(|id| !id.is_empty()),
+        })
+    }
+
+    fn from_file(path: PathBuf) -> Result<CredentialSource, FcmError> {
+        let json = fs::read_to_string(&path)
+            .map_err(|e| FcmError::authentication(format!("cannot read credentials {}", path.display()), e))?;
+
+        let value: Value = serde_json::from_str(&json)
+            .map_err(|e| FcmError::authentication(format!("invalid credentials {}", path.display()), e))?;
+
+        let invalid =
+            |e: serde_json::Error| FcmError::authentication(format!("invalid credentials {}", path.display()), e);
+
+        match value.get("type").and_then(Value::as_str) {
+            Some("service_account") => Ok(CredentialSource::ServiceAccount(
+                serde_json::from_value(value).map_err(invalid)?,
+            )),
+            Some("authorized_user") => Ok(CredentialSource::AuthorizedUser(
+                serde_json::from_value(value).map_err(invalid)?,
+            )),
+            other => Err(FcmError::authentication_failed(format!(
+                "unsupported credentials type {:?} in {}",
+                other.unwrap_or("<none>"),
+                path.display()
+            ))),
+        }
+    }
+
+    fn provider(&self) -> &dyn TokenProvider {
+        match self.source {
+            CredentialSource::ServiceAccount(ref key) => key,
+            CredentialSource::AuthorizedUser(ref user) => user,
+            CredentialSource::MetadataServer(ref metadata) => metadata,
+        }
+    }
+}
+
+/// The location gcloud writes application default credentials to.
+fn well_known_file<F>(var: &F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let config_dir = match var("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(var("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(var("HOME")?).join(".config").join("gcloud"),
+    };
+
+    Some(config_dir.join(WELL_KNOWN_FILE))
+}
+
+#[async_trait]
+impl TokenProvider for ApplicationDefaultCredentials {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        self.provider().token(http_client).await
+    }
+
+    async fn project_id(&self, http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        match self.project_id {
+            Some(ref project_id) => Ok(Some(project_id.clone())),
+            None => self.provider().project_id(http_client).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const KEY_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/service_account.json");
+
+    fn discover(vars: &[(&str, &str)]) -> Result<ApplicationDefaultCredentials, FcmError> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        ApplicationDefaultCredentials::discover_with(|name| vars.get(name).cloned())
+    }
+
+    fn temp_config_dir(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fcm-adc-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(WELL_KNOWN_FILE), contents).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_discover_from_env_var() {
+        let credentials = discover(&[(CREDENTIALS_ENV, KEY_FILE)]).unwrap();
+
+        assert!(matches!(credentials.source, CredentialSource::ServiceAccount(_)));
+        assert_eq!(
+            Some("fcm-test-project".to_string()),
+            credentials.project_id(&reqwest::Client::new()).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_env_var_wins() {
+        let credentials = discover(&[(CREDENTIALS_ENV, KEY_FILE), (PROJECT_ENV, "other-project")]).unwrap();
+
+        assert_eq!(
+            Some("other-project".to_string()),
+            credentials.project_id(&reqwest::Client::new()).await.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_missing_env_var_file_is_an_error() {
+        assert!(discover(&[(CREDENTIALS_ENV, "/does/not/exist.json")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_discover_well_known_file() {
+        let dir = temp_config_dir(
+            "well-known",
+            r#"{
+                "type": "authorized_user",
+                "client_id": "client",
+                "client_secret": "secret",
+                "refresh_token": "refresh",
+                "quota_project_id": "quota-project"
+            }"#,
+        );
+
+        let credentials = discover(&[("CLOUDSDK_CONFIG", dir.to_str().unwrap())]).unwrap();
+
+        assert!(matches!(credentials.source, CredentialSource::AuthorizedUser(_)));
+        assert_eq!(
+            Some("quota-project".to_string()),
+            credentials.project_id(&reqwest::Client::new()).await.unwrap()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_credentials_type() {
+        let dir = temp_config_dir("unsupported", r#"{"type": "external_account"}"#);
+
+        match discover(&[("CLOUDSDK_CONFIG", dir.to_str().unwrap())]) {
+            Err(FcmError::Authentication { context, .. }) => assert!(context.contains("external_account")),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fall_back_to_metadata_server() {
+        let credentials = discover(&[("HOME", "/does/not/exist"), (METADATA_HOST_ENV, "127.0.0.1:1")]).unwrap();
+
+        assert_eq!(
+            CredentialSource::MetadataServer(MetadataServerProvider::new().with_host("http://127.0.0.1:1")),
+            credentials.source
+        );
+    }
+}
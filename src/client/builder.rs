@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Proxy, Url};
+
+use crate::client::adc::ApplicationDefaultCredentials;
+use crate::client::oauth::ServiceAccountKey;
+use crate::client::provider::TokenProvider;
+use crate::client::response::FcmError;
+use crate::client::retry::RetryPolicy;
+use crate::client::{Client, FCM_ENDPOINT};
+
+const DEFAULT_USER_AGENT: &str = concat!("fcm-rust/", env!("CARGO_PKG_VERSION"));
+
+/// A builder to get a `Client` instance.
+///
+/// Without a token provider, credentials are discovered like the Google
+/// Cloud client libraries do, see `ApplicationDefaultCredentials`. Without a
+/// project id, the project of the credentials is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fcm::ClientBuilder;
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), fcm::Error> {
+/// let mut builder = ClientBuilder::new();
+/// builder
+///     .project_id("<project id>")
+///     .timeout(Duration::from_secs(10))
+///     .default_header("x-goog-user-project", "<billing project id>");
+/// let client = builder.build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ClientBuilder {
+    project_id: Option<String>,
+    token_provider: Option<Box<dyn TokenProvider>>,
+    base_url: Option<String>,
+    default_headers: Vec<(String, String)>,
+    http_client: Option<reqwest::Client>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl ClientBuilder {
+    /// Get a new `ClientBuilder` instance.
+    pub fn new() -> ClientBuilder {
+        Self::default()
+    }
+
+    /// The Firebase project messages are sent through.
+    pub fn project_id<S: Into<String>>(&mut self, project_id: S) -> &mut Self {
+        self.project_id = Some(project_id.into());
+        self
+    }
+
+    /// Ask the given provider for access tokens.
+    pub fn token_provider<P: TokenProvider + 'static>(&mut self, token_provider: P) -> &mut Self {
+        self.token_provider = Some(Box::new(token_provider));
+        self
+    }
+
+    /// Authenticate as the given service account.
+    pub fn service_account_key(&mut self, key: ServiceAccountKey) -> &mut Self {
+        self.token_provider(key)
+    }
+
+    /// Send messages to another server than `https://fcm.googleapis.com`,
+    /// such as a local fake or an egress proxy.
+    pub fn base_url<S: Into<String>>(&mut self, base_url: S) -> &mut Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Add a header to every request sent to FCM, for example
+    /// `x-goog-user-project`.
+    pub fn default_header<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) -> &mut Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send requests through an existing `reqwest::Client`, sharing its
+    /// connection pool. The connection settings of this builder can't be
+    /// combined with it.
+    pub fn http_client(&mut self, http_client: reqwest::Client) -> &mut Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// How long to wait for a connection to be established.
+    pub fn connect_timeout(&mut self, connect_timeout: Duration) -> &mut Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// How long to wait for a whole request, from connecting until the
+    /// response body is read.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send all requests through the given HTTP proxy.
+    pub fn proxy<S: Into<String>>(&mut self, proxy_url: S) -> &mut Self {
+        self.proxy = Some(proxy_url.into());
+        self
+    }
+
+    /// The `User-Agent` header sent with every request.
+    pub fn user_agent<S: Into<String>>(&mut self, user_agent: S) -> &mut Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// The maximum number of idle connections kept per host. Unlimited by
+    /// default.
+    pub fn pool_max_idle_per_host(&mut self, max: usize) -> &mut Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// How long an idle connection is kept in the pool.
+    pub fn pool_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry messages FCM could not process according to the given policy.
+    /// Without one, errors are returned right away.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Complete the build and get a `Client` instance. Fails if the settings
+    /// are invalid, or if no credentials are given and none can be
+    /// discovered.
+    pub fn build(self) -> Result<Client, FcmError> {
+        let endpoint = match self.base_url {
+            Some(ref base_url) => {
+                Url::parse(base_url)
+                    .map_err(|e| FcmError::InvalidConfiguration(format!("invalid base url {}: {}", base_url, e)))?;
+                base_url.trim_end_matches('/').to_string()
+            }
+            None => FCM_ENDPOINT.to_string(),
+        };
+
+        let mut default_headers = HeaderMap::new();
+
+        for (name, value) in self.default_headers.iter() {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| FcmError::InvalidConfiguration(format!("invalid header name {}: {}", name, e)))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|e| FcmError::InvalidConfiguration(format!("invalid value for header {}: {}", name, e)))?;
+
+            default_headers.append(header_name, header_value);
+        }
+
+        let http_client = match self.http_client {
+            Some(http_client) => {
+                let has_connection_settings = self.connect_timeout.is_some()
+                    || self.timeout.is_some()
+                    || self.proxy.is_some()
+                    || self.user_agent.is_some()
+                    || self.pool_max_idle_per_host.is_some()
+                    || self.pool_idle_timeout.is_some();
+
+                if has_connection_settings {
+                    return Err(FcmError::InvalidConfiguration(
+                        "connection settings can't be applied to an injected HTTP client".to_string(),
+                    ));
+                }
+
+                http_client
+            }
+            None => {
+                let mut builder = reqwest::ClientBuilder::new()
+                    .pool_max_idle_per_host(self.pool_max_idle_per_host.unwrap_or(usize::MAX))
+                    .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+
+                if let Some(connect_timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(connect_timeout);
+                }
+
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+
+                if let Some(ref proxy) = self.proxy {
+                    builder = builder.proxy(Proxy::all(proxy).map_err(FcmError::HttpClient)?);
+                }
+
+                if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+                    builder = builder.pool_idle_timeout(pool_idle_timeout);
+                }
+
+                builder.build().map_err(FcmError::HttpClient)?
+            }
+        };
+
+        let token_provider = match self.token_provider {
+            Some(token_provider) => token_provider,
+            None => Box::new(ApplicationDefaultCredentials::discover()?),
+        };
+
+        Ok(Client::from_parts(
+            http_client,
+            endpoint,
+            default_headers,
+            self.project_id,
+            token_provider,
+            self.retry_policy,
+        ))
+    }
+}
@@ -1,49 +1,176 @@
 pub mod response;
 
+mod adc;
+pub use crate::client::adc::ApplicationDefaultCredentials;
+mod builder;
+pub use crate::client::builder::ClientBuilder;
+mod oauth;
+pub use crate::client::oauth::*;
+mod provider;
+pub use crate::client::provider::*;
+mod retry;
+pub use crate::client::retry::{Clock, RetryPolicy, SystemClock};
+mod token;
 pub use crate::client::response::*;
+pub use crate::client::token::AccessToken;
+use crate::client::token::TokenManager;
 
 use crate::message::Message;
-use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
-use reqwest::{Body, StatusCode};
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Body, Response, StatusCode};
+use std::borrow::Borrow;
+use tokio::sync::OnceCell;
+
+#[cfg(test)]
+mod tests;
+
+const FCM_ENDPOINT: &str = "https://fcm.googleapis.com";
 
 /// An async client for sending the notification payload.
+///
+/// `Client` no longer implements `Default`, since a client needs
+/// credentials to send anything. Use `Client::from_env` for application
+/// default credentials, or `Client::builder` to give them explicitly.
 pub struct Client {
     http_client: reqwest::Client,
-    project_id: String,
-    token: String,
+    endpoint: String,
+    default_headers: HeaderMap,
+    project_id: OnceCell<String>,
+    tokens: TokenManager,
+    retry_policy: Option<RetryPolicy>,
 }
 
-impl Default for Client {
-    fn default() -> Self {
-        Self::new("".to_string(), "".to_string())
+impl Client {
+    /// Get a new instance of Client. Fails if the HTTP client can't be set
+    /// up, for example when no TLS backend is available.
+    pub fn new(project_id: String, token: String) -> Result<Client, FcmError> {
+        Self::with_token_provider(project_id, StaticTokenProvider::new(token))
     }
-}
 
-impl Client {
-    /// Get a new instance of Client.
-    pub fn new(project_id: String, token: String) -> Client {
-        let http_client = reqwest::ClientBuilder::new()
-            .pool_max_idle_per_host(std::usize::MAX)
-            .build()
-            .unwrap();
-
-        Client { http_client, project_id, token }
+    /// Get a new instance of Client, authenticating as the given service
+    /// account. Access tokens are requested from the key's `token_uri` and
+    /// cached until shortly before they expire.
+    pub fn with_service_account(project_id: String, key: ServiceAccountKey) -> Result<Client, FcmError> {
+        Self::with_token_provider(project_id, key)
+    }
+
+    /// Get a new instance of Client, asking the given provider for access
+    /// tokens. Tokens are cached until shortly before they expire.
+    pub fn with_token_provider<P: TokenProvider + 'static>(
+        project_id: String,
+        provider: P,
+    ) -> Result<Client, FcmError> {
+        let mut builder = ClientBuilder::new();
+        builder.project_id(project_id).token_provider(provider);
+        builder.build()
+    }
+
+    /// Get a `ClientBuilder` to configure the endpoint, credentials and
+    /// connection settings of a new Client.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Get a new instance of Client using application default credentials,
+    /// and the project they belong to.
+    ///
+    /// This is a shorthand for `ClientBuilder::new().build()`.
+    pub fn from_env() -> Result<Client, FcmError> {
+        ClientBuilder::new().build()
+    }
+
+    fn from_parts(
+        http_client: reqwest::Client,
+        endpoint: String,
+        default_headers: HeaderMap,
+        project_id: Option<String>,
+        provider: Box<dyn TokenProvider>,
+        retry_policy: Option<RetryPolicy>,
+    ) -> Client {
+        Client {
+            http_client,
+            endpoint,
+            default_headers,
+            project_id: OnceCell::new_with(project_id),
+            tokens: TokenManager::new(provider),
+            retry_policy,
+        }
+    }
+
+    /// The project id given at construction, or else the one of the
+    /// credentials, asked for once.
+    async fn project_id(&self) -> Result<&str, FcmError> {
+        self.project_id
+            .get_or_try_init(|| async {
+                self.tokens
+                    .project_id(&self.http_client)
+                    .await?
+                    .ok_or_else(|| FcmError::authentication_failed("no project id given or found in credentials"))
+            })
+            .await
+            .map(String::as_str)
     }
 
     /// Try sending a `Message` to FCM.
-    pub async fn send(&self, message: Message<'_>) -> Result<FcmResponse, FcmError> {
-        let payload = serde_json::to_vec(&message).unwrap();
+    ///
+    /// The message is first checked with `Message::validate`, and not sent
+    /// if invalid. If FCM rejects the access token, a fresh one is fetched
+    /// and the message is sent once more. If the client has a `RetryPolicy`,
+    /// errors worth retrying are retried according to it.
+    ///
+    /// The message is only borrowed, so the same message, or an `Arc` of it,
+    /// can be sent again or to several clients without rebuilding it.
+    pub async fn send(&self, message: impl Borrow<Message<'_>>) -> Result<SendResponse, FcmError> {
+        let message = message.borrow();
+        message.validate()?;
 
-        let request = self
-            .http_client
-            .post(format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", self.project_id))
-            .header(CONTENT_TYPE, "application/json")
-            .header(CONTENT_LENGTH, format!("{}", payload.len() as u64).as_bytes())
-            .header(AUTHORIZATION, format!("Bearer {}", self.token).as_bytes())
-            .body(Body::from(payload))
-            .build()?;
-        let response = self.http_client.execute(request).await?;
+        let payload = serde_json::to_vec(&message).map_err(FcmError::Serialization)?;
+        let registration_token = message.token();
+
+        let retry_policy = match self.retry_policy {
+            Some(ref retry_policy) => retry_policy,
+            None => return self.send_once(&payload, registration_token).await,
+        };
 
+        let started = retry_policy.now();
+        let mut attempt = 1;
+
+        loop {
+            match self.send_once(&payload, registration_token).await {
+                Err(error) => match retry_policy.next_delay(&error, attempt, started) {
+                    Some(delay) => {
+                        log::debug!("attempt {} failed with {}, retrying in {:?}", attempt, error, delay);
+                        retry_policy.sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once(&self, payload: &[u8], registration_token: Option<&str>) -> Result<SendResponse, FcmError> {
+        let token = self.tokens.token(&self.http_client).await?;
+
+        match self.send_with_token(payload, &token, registration_token).await {
+            Err(FcmError::Unauthorized(_)) if self.tokens.can_refresh() => {
+                self.tokens.invalidate(&token).await;
+
+                let token = self.tokens.token(&self.http_client).await?;
+                self.send_with_token(payload, &token, registration_token).await
+            }
+            result => result,
+        }
+    }
+
+    async fn send_with_token(
+        &self,
+        payload: &[u8],
+        token: &str,
+        registration_token: Option<&str>,
+    ) -> Result<SendResponse, FcmError> {
+        let response = self.execute(payload, token).await?;
         let response_status = response.status();
 
         let retry_after = response
@@ -52,20 +179,32 @@ impl Client {
             .and_then(|ra| ra.to_str().ok())
             .and_then(|ra| ra.parse::<RetryAfter>().ok());
 
-        match response_status {
-            StatusCode::OK => {
-                let fcm_response: FcmResponse = response.json().await.unwrap();
+        let body = response.bytes().await?;
 
-                match fcm_response.error {
-                    Some(ErrorReason::Unavailable) => Err(response::FcmError::ServerError(retry_after)),
-                    Some(ErrorReason::InternalServerError) => Err(response::FcmError::ServerError(retry_after)),
-                    _ => Ok(fcm_response),
-                }
-            }
-            StatusCode::UNAUTHORIZED => Err(response::FcmError::Unauthorized),
-            StatusCode::BAD_REQUEST => Err(response::FcmError::InvalidMessage("Bad Request".to_string())),
-            status if status.is_server_error() => Err(response::FcmError::ServerError(retry_after)),
-            _ => Err(response::FcmError::InvalidMessage("Unknown Error".to_string())),
+        if response_status == StatusCode::OK {
+            return serde_json::from_slice(&body).map_err(FcmError::InvalidResponse);
         }
+
+        Err(response::FcmError::from_api_error(
+            ApiError::from_response(response_status.as_u16(), &body),
+            retry_after,
+            registration_token,
+        ))
+    }
+
+    async fn execute(&self, payload: &[u8], token: &str) -> Result<Response, FcmError> {
+        let project_id = self.project_id().await?;
+
+        let request = self
+            .http_client
+            .post(format!("{}/v1/projects/{}/messages:send", self.endpoint, project_id))
+            .headers(self.default_headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_LENGTH, format!("{}", payload.len() as u64).as_bytes())
+            .header(AUTHORIZATION, format!("Bearer {}", token).as_bytes())
+            .body(Body::from(payload.to_vec()))
+            .build()?;
+
+        Ok(self.http_client.execute(request).await?)
     }
 }
@@ -0,0 +1,342 @@
+use std::{fmt, fs, path::Path};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+
+use crate::client::provider::{request_token, TokenProvider};
+use crate::client::response::FcmError;
+use crate::client::token::AccessToken;
+
+/// Google's OAuth2 token endpoint, used when the key does not name one.
+pub const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// The OAuth2 scope needed to send messages through FCM.
+pub const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+
+const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// Lifetime of the signed assertion, the maximum Google accepts.
+const ASSERTION_LIFETIME_SECS: i64 = 3600;
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_string()
+}
+
+/// A service account key, as downloaded from the Google Cloud console.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fcm::{Client, ServiceAccountKey};
+///
+/// # fn main() -> Result<(), fcm::Error> {
+/// let key = ServiceAccountKey::from_file("service-account.json")?;
+/// let client = Client::with_service_account("<project id>".to_string(), key)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct ServiceAccountKey {
+    /// The type of the key, `service_account` for keys of this kind.
+    #[serde(rename = "type", default)]
+    pub key_type: Option<String>,
+
+    /// The project the service account belongs to.
+    #[serde(default)]
+    pub project_id: Option<String>,
+
+    /// The id of the private key, sent as `kid` in the assertion header.
+    #[serde(default)]
+    pub private_key_id: Option<String>,
+
+    /// The PEM encoded RSA private key.
+    pub private_key: String,
+
+    /// The email address of the service account.
+    pub client_email: String,
+
+    /// The endpoint the signed assertion is exchanged at. Override this to
+    /// talk to a different token server.
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceAccountKey")
+            .field("key_type", &self.key_type)
+            .field("project_id", &self.project_id)
+            .field("private_key_id", &self.private_key_id)
+            .field("private_key", &"<redacted>")
+            .field("client_email", &self.client_email)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+impl ServiceAccountKey {
+    /// Parse a service account key from its JSON representation.
+    pub fn from_json(json: &str) -> Result<Self, FcmError> {
+        serde_json::from_str(json).map_err(|e| FcmError::authentication("invalid service account key", e))
+    }
+
+    /// Read a service account key from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FcmError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| FcmError::authentication(format!("cannot read service account key {}", path.display()), e))?;
+
+        Self::from_json(&json)
+    }
+
+    /// Replace the endpoint the signed assertion is exchanged at.
+    pub fn with_token_uri<S: Into<String>>(mut self, token_uri: S) -> Self {
+        self.token_uri = token_uri.into();
+        self
+    }
+
+    /// Sign an RS256 assertion requesting the FCM scope, valid for one hour
+    /// from now.
+    pub fn sign_assertion(&self) -> Result<String, FcmError> {
+        let key = EncodingKey::from_rsa_pem(self.private_key.as_bytes())
+            .map_err(|e| FcmError::authentication("invalid private key", e))?;
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = self.private_key_id.clone();
+
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            iss: &self.client_email,
+            scope: FCM_SCOPE,
+            aud: &self.token_uri,
+            iat: now,
+            exp: now + ASSERTION_LIFETIME_SECS,
+        };
+
+        jsonwebtoken::encode(&header, &claims, &key).map_err(|e| FcmError::authentication("cannot sign assertion", e))
+    }
+
+    /// Exchange a freshly signed assertion for an access token at the
+    /// `token_uri`.
+    pub async fn fetch_access_token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        let assertion = self.sign_assertion()?;
+
+        let request = http_client
+            .post(&self.token_uri)
+            .form(&[("grant_type", JWT_BEARER_GRANT_TYPE), ("assertion", &assertion)]);
+
+        request_token(request, "token endpoint").await
+    }
+}
+
+#[async_trait]
+impl TokenProvider for ServiceAccountKey {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        self.fetch_access_token(http_client).await
+    }
+
+    async fn project_id(&self, _: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        Ok(self.project_id.clone())
+    }
+}
+
+/// The credentials of a user, as written by `gcloud auth
+/// application-default login`.
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct AuthorizedUserCredentials {
+    /// The OAuth2 client the refresh token was issued to.
+    pub client_id: String,
+
+    /// The secret of the OAuth2 client.
+    pub client_secret: String,
+
+    /// The long lived token exchanged for access tokens.
+    pub refresh_token: String,
+
+    /// The project billed for requests, used as the default project.
+    #[serde(default)]
+    pub quota_project_id: Option<String>,
+
+    /// The endpoint the refresh token is exchanged at.
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+impl fmt::Debug for AuthorizedUserCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorizedUserCredentials")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("refresh_token", &"<redacted>")
+            .field("quota_project_id", &self.quota_project_id)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+impl AuthorizedUserCredentials {
+    /// Replace the endpoint the refresh token is exchanged at.
+    pub fn with_token_uri<S: Into<String>>(mut self, token_uri: S) -> Self {
+        self.token_uri = token_uri.into();
+        self
+    }
+}
+
+#[async_trait]
+impl TokenProvider for AuthorizedUserCredentials {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        let request = http_client.post(&self.token_uri).form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+            ("refresh_token", &self.refresh_token),
+        ]);
+
+        request_token(request, "token endpoint").await
+    }
+
+    async fn project_id(&self, _: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        Ok(self.quota_project_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use jsonwebtoken::{DecodingKey, Validation};
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const KEY_JSON: &str = include_str!("../../tests/fixtures/service_account.json");
+    const PUBLIC_KEY: &str = include_str!("../../tests/fixtures/service_account.pub.pem");
+
+    #[test]
+    fn test_parse_service_account_key() {
+        let key = ServiceAccountKey::from_json(KEY_JSON).unwrap();
+
+        assert_eq!(Some("service_account".to_string()), key.key_type);
+        assert_eq!(Some("fcm-test-project".to_string()), key.project_id);
+        assert_eq!("fcm-sender@fcm-test-project.iam.gserviceaccount.com", key.client_email);
+        assert_eq!(DEFAULT_TOKEN_URI, key.token_uri);
+        assert!(!format!("{:?}", key).contains("PRIVATE KEY"));
+    }
+
+    #[test]
+    fn test_sign_assertion() {
+        let key = ServiceAccountKey::from_json(KEY_JSON).unwrap();
+        let assertion = key.sign_assertion().unwrap();
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[DEFAULT_TOKEN_URI]);
+
+        let decoded = jsonwebtoken::decode::<Value>(
+            &assertion,
+            &DecodingKey::from_rsa_pem(PUBLIC_KEY.as_bytes()).unwrap(),
+            &validation,
+        )
+        .unwrap();
+
+        assert_eq!(Some("0123456789abcdef".to_string()), decoded.header.kid);
+        assert_eq!(json!(key.client_email), decoded.claims["iss"]);
+        assert_eq!(json!(FCM_SCOPE), decoded.claims["scope"]);
+        assert_eq!(
+            decoded.claims["iat"].as_i64().unwrap() + ASSERTION_LIFETIME_SECS,
+            decoded.claims["exp"].as_i64().unwrap(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_access_token() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(
+                "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer",
+            ))
+            .and(body_string_contains("assertion="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.token",
+                "expires_in": 3599,
+                "token_type": "Bearer",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let key = ServiceAccountKey::from_json(KEY_JSON)
+            .unwrap()
+            .with_token_uri(format!("{}/token", server.uri()));
+
+        let token = key.fetch_access_token(&reqwest::Client::new()).await.unwrap();
+
+        assert_eq!("ya29.token", token.token);
+        assert!(token.expires_within(Duration::seconds(3600)));
+        assert!(!token.expires_within(Duration::seconds(3500)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_access_token_rejected() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("invalid_grant"))
+            .mount(&server)
+            .await;
+
+        let key = ServiceAccountKey::from_json(KEY_JSON)
+            .unwrap()
+            .with_token_uri(server.uri());
+
+        match key.fetch_access_token(&reqwest::Client::new()).await {
+            Err(FcmError::Authentication { context, .. }) => assert!(context.contains("invalid_grant")),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_keep_the_cause_of_invalid_keys() {
+        let error = ServiceAccountKey::from_json("{").unwrap_err();
+
+        assert!(matches!(error, FcmError::Authentication { .. }));
+        assert!(std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<serde_json::Error>())
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_access_token_timeout() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let key = ServiceAccountKey::from_json(KEY_JSON)
+            .unwrap()
+            .with_token_uri(server.uri());
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        match key.fetch_access_token(&http_client).await {
+            Err(FcmError::Transport(e)) => assert!(e.is_timeout()),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::client::response::FcmError;
+use crate::client::token::AccessToken;
+
+/// The metadata server of Google Compute Engine, Cloud Run, GKE and friends.
+pub const DEFAULT_METADATA_HOST: &str = "http://metadata.google.internal";
+
+/// Overrides the metadata server host, like the Google Cloud client libraries.
+pub(crate) const METADATA_HOST_ENV: &str = "GCE_METADATA_HOST";
+
+const METADATA_TOKEN_PATH: &str = "/computeMetadata/v1/instance/service-accounts/default/token";
+
+const METADATA_PROJECT_ID_PATH: &str = "/computeMetadata/v1/project/project-id";
+
+/// A source of OAuth2 access tokens, consulted by `Client` before each
+/// request. Tokens are cached by the client until shortly before they
+/// expire, so implementations don't need to cache themselves.
+///
+/// The trait is async through `async_trait`, re-exported as
+/// `fcm::async_trait` for implementors.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{AccessToken, Client, TokenProvider};
+///
+/// struct Vault;
+///
+/// #[fcm::async_trait]
+/// impl TokenProvider for Vault {
+///     async fn token(&self, _: &reqwest::Client) -> Result<AccessToken, fcm::Error> {
+///         Ok(AccessToken::new("<token from the vault>"))
+///     }
+/// }
+///
+/// # fn main() -> Result<(), fcm::Error> {
+/// let client = Client::with_token_provider("<project id>".to_string(), Vault)?;
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Obtain a new access token, using the client's HTTP connection pool
+    /// when a request is needed.
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError>;
+
+    /// Whether asking again can yield a different token. When it can't, a
+    /// token rejected by FCM is not retried.
+    fn can_refresh(&self) -> bool {
+        true
+    }
+
+    /// The project the credentials belong to, if known. It is asked for
+    /// once, when the client was not given a project id.
+    async fn project_id(&self, _http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl<T: TokenProvider + ?Sized> TokenProvider for Arc<T> {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        (**self).token(http_client).await
+    }
+
+    fn can_refresh(&self) -> bool {
+        (**self).can_refresh()
+    }
+
+    async fn project_id(&self, http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        (**self).project_id(http_client).await
+    }
+}
+
+#[async_trait]
+impl<T: TokenProvider + ?Sized> TokenProvider for Box<T> {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        (**self).token(http_client).await
+    }
+
+    fn can_refresh(&self) -> bool {
+        (**self).can_refresh()
+    }
+
+    async fn project_id(&self, http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        (**self).project_id(http_client).await
+    }
+}
+
+/// The body of a successful OAuth2 token response, shared by the token
+/// endpoint and the metadata server.
+#[derive(Deserialize)]
+pub(crate) struct TokenResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+}
+
+impl From<TokenResponse> for AccessToken {
+    fn from(response: TokenResponse) -> AccessToken {
+        match response.expires_in {
+            Some(expires_in) => AccessToken::expiring_in(response.access_token, chrono::Duration::seconds(expires_in)),
+            None => AccessToken::new(response.access_token),
+        }
+    }
+}
+
+/// Send a token request and read the token from a successful answer.
+/// `source` names the server in error messages. A request that gets no
+/// answer is a `Transport` error, so that it can be retried.
+pub(crate) async fn request_token(request: reqwest::RequestBuilder, source: &str) -> Result<AccessToken, FcmError> {
+    let response = request.send().await?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(FcmError::authentication_failed(format!(
+            "{} answered {}: {}",
+            source, status, body
+        )));
+    }
+
+    let token: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| FcmError::authentication(format!("invalid {} response", source), e))?;
+
+    Ok(token.into())
+}
+
+/// A fixed access token, obtained elsewhere. It is never refreshed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StaticTokenProvider {
+    token: String,
+}
+
+impl StaticTokenProvider {
+    pub fn new<S: Into<String>>(token: S) -> StaticTokenProvider {
+        StaticTokenProvider { token: token.into() }
+    }
+}
+
+#[async_trait]
+impl TokenProvider for StaticTokenProvider {
+    async fn token(&self, _: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        Ok(AccessToken::new(self.token.clone()))
+    }
+
+    fn can_refresh(&self) -> bool {
+        false
+    }
+}
+
+/// Tokens of the default service account attached to the instance, asked
+/// from the metadata server.
+///
+/// The host is taken from `GCE_METADATA_HOST` if set, and can be replaced
+/// with `with_host`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MetadataServerProvider {
+    host: String,
+}
+
+impl Default for MetadataServerProvider {
+    fn default() -> Self {
+        let host = env::var(METADATA_HOST_ENV)
+            .map(|host| format!("http://{}", host))
+            .unwrap_or_else(|_| DEFAULT_METADATA_HOST.to_string());
+
+        MetadataServerProvider { host }
+    }
+}
+
+impl MetadataServerProvider {
+    pub fn new() -> MetadataServerProvider {
+        Self::default()
+    }
+
+    /// Talk to the metadata server at the given base URL, such as
+    /// `http://127.0.0.1:8080`.
+    pub fn with_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.host = host.into();
+        self
+    }
+}
+
+#[async_trait]
+impl TokenProvider for MetadataServerProvider {
+    async fn token(&self, http_client: &reqwest::Client) -> Result<AccessToken, FcmError> {
+        let request = http_client
+            .get(format!("{}{}", self.host, METADATA_TOKEN_PATH))
+            .header("Metadata-Flavor", "Google");
+
+        request_token(request, "metadata server").await
+    }
+
+    async fn project_id(&self, http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        let response = http_client
+            .get(format!("{}{}", self.host, METADATA_PROJECT_ID_PATH))
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let project_id = response
+            .text()
+            .await
+            .map_err(|e| FcmError::authentication("invalid metadata server response", e))?;
+
+        Ok(Some(project_id.trim().to_string()).filter(|id| !id.is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_static_token() {
+        let provider = StaticTokenProvider::new("static");
+
+        assert_eq!(
+            AccessToken::new("static"),
+            provider.token(&reqwest::Client::new()).await.unwrap()
+        );
+        assert!(!provider.can_refresh());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_token() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path(METADATA_TOKEN_PATH))
+            .and(header("Metadata-Flavor", "Google"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.metadata",
+                "expires_in": 1800,
+                "token_type": "Bearer",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = MetadataServerProvider::new().with_host(server.uri());
+        let token = provider.token(&reqwest::Client::new()).await.unwrap();
+
+        assert_eq!("ya29.metadata", token.token);
+        assert!(token.expires_within(Duration::seconds(1800)));
+        assert!(provider.can_refresh());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_project_id() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path(METADATA_PROJECT_ID_PATH))
+            .and(header("Metadata-Flavor", "Google"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("metadata-project"))
+            .mount(&server)
+            .await;
+
+        let provider = MetadataServerProvider::new().with_host(server.uri());
+
+        assert_eq!(
+            Some("metadata-project".to_string()),
+            provider.project_id(&reqwest::Client::new()).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("no service account"))
+            .mount(&server)
+            .await;
+
+        let provider = MetadataServerProvider::new().with_host(server.uri());
+
+        match provider.token(&reqwest::Client::new()).await {
+            Err(FcmError::Authentication { context, .. }) => assert!(context.contains("no service account")),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -1,9 +1,15 @@
-pub use chrono::{DateTime, Duration, FixedOffset};
+//! Types of the legacy HTTP API, which FCM shut down in favour of the v1
+//! API. They are kept to ease migration and will be removed.
+//!
+//! Replace `FcmResponse` with `SendResponse`, and `ErrorReason` with
+//! `ErrorCode` read from `FcmError::error_code`.
+#![allow(deprecated)]
+
 use serde::Deserialize;
-use std::{error::Error, fmt, str::FromStr};
 
 /// A description of what went wrong with the push notification.
 /// Referred from [Firebase documentation](https://firebase.google.com/docs/cloud-messaging/http-server-ref#table9)
+#[deprecated(note = "the legacy HTTP API is shut down, use `ErrorCode`")]
 #[derive(Deserialize, Debug, PartialEq, Copy, Clone)]
 pub enum ErrorReason {
     /// Check that the request contains a registration token (in the `to` or
@@ -91,6 +97,7 @@ pub enum ErrorReason {
     InvalidApnsCredential,
 }
 
+#[deprecated(note = "the legacy HTTP API is shut down, use `SendResponse`")]
 #[derive(Deserialize, Debug)]
 pub struct FcmResponse {
     pub message_id: Option<u64>,
@@ -102,6 +109,7 @@ pub struct FcmResponse {
     pub results: Option<Vec<MessageResult>>,
 }
 
+#[deprecated(note = "the legacy HTTP API is shut down, use `SendResponse`")]
 #[derive(Deserialize, Debug)]
 pub struct MessageResult {
     pub message_id: Option<String>,
@@ -109,86 +117,9 @@ pub struct MessageResult {
     pub error: Option<ErrorReason>,
 }
 
-/// Fatal errors. Referred from [Firebase
-/// documentation](https://firebase.google.com/docs/cloud-messaging/http-server-ref#table9)
-#[derive(PartialEq, Debug)]
-pub enum FcmError {
-    /// The sender account used to send a message couldn't be authenticated. Possible causes are:
-    ///
-    /// Authorization header missing or with invalid syntax in HTTP request.
-    ///
-    /// * The Firebase project that the specified server key belongs to is
-    ///   incorrect.
-    /// * Legacy server keys only—the request originated from a server not
-    ///   whitelisted in the Server key IPs.
-    ///
-    /// Check that the token you're sending inside the Authentication header is
-    /// the correct Server key associated with your project. See Checking the
-    /// validity of a Server key for details. If you are using a legacy server
-    /// key, you're recommended to upgrade to a new key that has no IP
-    /// restrictions.
-    Unauthorized,
-
-    /// Check that the JSON message is properly formatted and contains valid
-    /// fields (for instance, making sure the right data type is passed in).
-    InvalidMessage(String),
-
-    /// The server couldn't process the request. Retry the same request, but you must:
-    ///
-    /// * Honor the [RetryAfter](enum.RetryAfter.html) value if included.
-    /// * Implement exponential back-off in your retry mechanism. (e.g. if you
-    ///   waited one second before the first retry, wait at least two second
-    ///   before the next one, then 4 seconds and so on). If you're sending
-    ///   multiple messages, delay each one independently by an additional random
-    ///   amount to avoid issuing a new request for all messages at the same time.
-    ///
-    /// Senders that cause problems risk being blacklisted.
-    ServerError(Option<RetryAfter>),
-}
-
-impl Error for FcmError {}
-
-impl fmt::Display for FcmError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            FcmError::Unauthorized => write!(f, "authorization header missing or with invalid syntax in HTTP request"),
-            FcmError::InvalidMessage(ref s) => write!(f, "invalid message {}", s),
-            FcmError::ServerError(_) => write!(f, "the server couldn't process the request"),
-        }
-    }
-}
-
-impl From<reqwest::Error> for FcmError {
-    fn from(_: reqwest::Error) -> Self {
-        Self::ServerError(None)
-    }
-}
-
-#[derive(PartialEq, Debug)]
-pub enum RetryAfter {
-    /// Amount of time to wait until retrying the message is allowed.
-    Delay(Duration),
-
-    /// A point in time until retrying the message is allowed.
-    DateTime(DateTime<FixedOffset>),
-}
-
-impl FromStr for RetryAfter {
-    type Err = crate::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse::<i64>()
-            .map(Duration::seconds)
-            .map(RetryAfter::Delay)
-            .or_else(|_| DateTime::parse_from_rfc2822(s).map(RetryAfter::DateTime))
-            .map_err(|e| crate::Error::InvalidMessage(format!("{}", e)))
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{DateTime, Duration};
     use serde_json::json;
 
     #[test]
@@ -221,25 +152,9 @@ mod tests {
             let response_string = serde_json::to_string(&response_data).unwrap();
             let fcm_response: FcmResponse = serde_json::from_str(&response_string).unwrap();
 
-            assert_eq!(Some(error_enum.clone()), fcm_response.results.unwrap()[0].error,);
+            assert_eq!(Some(error_enum), fcm_response.results.unwrap()[0].error,);
 
             assert_eq!(Some(error_enum), fcm_response.error,)
         }
     }
-
-    #[test]
-    fn test_retry_after_from_seconds() {
-        assert_eq!(RetryAfter::Delay(Duration::seconds(420)), "420".parse().unwrap());
-    }
-
-    #[test]
-    fn test_retry_after_from_date() {
-        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
-        let retry_after = RetryAfter::from_str(date).unwrap();
-
-        assert_eq!(
-            RetryAfter::DateTime(DateTime::parse_from_rfc2822(date).unwrap()),
-            retry_after,
-        );
-    }
 }
@@ -0,0 +1,687 @@
+pub use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Deserializer};
+use std::{error::Error, fmt, str::FromStr};
+
+pub mod legacy;
+#[allow(deprecated)]
+pub use self::legacy::{ErrorReason, FcmResponse, MessageResult};
+
+/// The answer of FCM to a message it accepted.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct SendResponse {
+    /// The identifier of the message sent.
+    pub name: MessageName,
+}
+
+/// The identifier FCM gives a message, in the format
+/// `projects/{project_id}/messages/{message_id}`.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::MessageName;
+///
+/// let name: MessageName = "projects/my-project/messages/0:1500415314455276%31bd1c9631bd1c96"
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!("my-project", name.project_id());
+/// assert_eq!("0:1500415314455276%31bd1c9631bd1c96", name.message_id());
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct MessageName {
+    project_id: String,
+    message_id: String,
+}
+
+impl MessageName {
+    /// The project the message was sent through.
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// The id of the message within the project.
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+}
+
+impl FromStr for MessageName {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(4, '/');
+
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("projects"), Some(project_id), Some("messages"), Some(message_id))
+                if !project_id.is_empty() && !message_id.is_empty() =>
+            {
+                Ok(MessageName {
+                    project_id: project_id.to_string(),
+                    message_id: message_id.to_string(),
+                })
+            }
+            _ => Err(crate::Error::InvalidMessage(format!("invalid message name {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for MessageName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "projects/{}/messages/{}", self.project_id, self.message_id)
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The error codes of the FCM v1 API, found in the `details` of an error
+/// response. Referred from [Firebase
+/// documentation](https://firebase.google.com/docs/reference/fcm/rest/v1/ErrorCode)
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// No more information is available about this error.
+    UnspecifiedError,
+
+    /// Request parameters were invalid. The field violations tell which
+    /// fields were rejected.
+    InvalidArgument,
+
+    /// The registration token is not valid anymore, for example because the
+    /// app was uninstalled. Stop sending messages to it.
+    Unregistered,
+
+    /// The registration token is tied to a different sender than the
+    /// project sending the message.
+    SenderIdMismatch,
+
+    /// The sending limit for the message target was exceeded. Retry with
+    /// exponential backoff.
+    QuotaExceeded,
+
+    /// The server is overloaded. Retry with exponential backoff, honoring
+    /// `Retry-After`.
+    Unavailable,
+
+    /// An unknown internal error occurred. Retry with exponential backoff.
+    Internal,
+
+    /// The APNs certificate or web push auth key was invalid or missing.
+    ThirdPartyAuthError,
+
+    /// A code this crate doesn't know yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A field of the request that was rejected.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct FieldViolation {
+    /// The path to the field, such as `message.android.ttl`.
+    #[serde(default)]
+    pub field: String,
+
+    /// Why the field was rejected.
+    #[serde(default)]
+    pub description: String,
+}
+
+impl FieldViolation {
+    pub(crate) fn new<F: Into<String>, D: Into<String>>(field: F, description: D) -> FieldViolation {
+        FieldViolation {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.description)
+    }
+}
+
+/// An error response of the FCM v1 API, a `google.rpc.Status`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ApiError {
+    /// The HTTP status code of the response.
+    pub status: u16,
+
+    /// The FCM specific error code, if the response included one.
+    pub error_code: Option<ErrorCode>,
+
+    /// The canonical status of the error, such as `INVALID_ARGUMENT`.
+    pub rpc_status: Option<String>,
+
+    /// A description of the error. The response body itself if it wasn't a
+    /// `google.rpc.Status`.
+    pub message: String,
+
+    /// The fields of the request that were rejected.
+    pub field_violations: Vec<FieldViolation>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: RpcStatus,
+}
+
+#[derive(Deserialize)]
+struct RpcStatus {
+    #[serde(default)]
+    message: String,
+    status: Option<String>,
+    #[serde(default)]
+    details: Vec<ErrorDetail>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorDetail {
+    #[serde(rename = "@type")]
+    type_url: String,
+    error_code: Option<ErrorCode>,
+    #[serde(default)]
+    field_violations: Vec<FieldViolation>,
+}
+
+const FCM_ERROR_TYPE: &str = "type.googleapis.com/google.firebase.fcm.v1.FcmError";
+const BAD_REQUEST_TYPE: &str = "type.googleapis.com/google.rpc.BadRequest";
+
+impl ApiError {
+    /// Read an error from the status and body of a response.
+    pub fn from_response(status: u16, body: &[u8]) -> ApiError {
+        match serde_json::from_slice::<ErrorBody>(body) {
+            Ok(ErrorBody { error }) => {
+                let error_code = error
+                    .details
+                    .iter()
+                    .filter(|detail| detail.type_url == FCM_ERROR_TYPE)
+                    .find_map(|detail| detail.error_code);
+
+                let field_violations = error
+                    .details
+                    .into_iter()
+                    .filter(|detail| detail.type_url == BAD_REQUEST_TYPE)
+                    .flat_map(|detail| detail.field_violations)
+                    .collect();
+
+                ApiError {
+                    status,
+                    error_code,
+                    rpc_status: error.status,
+                    message: error.message,
+                    field_violations,
+                }
+            }
+            Err(_) => ApiError {
+                status,
+                error_code: None,
+                rpc_status: None,
+                message: String::from_utf8_lossy(body).into_owned(),
+                field_violations: Vec::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FCM answered {}", self.status)?;
+
+        if let Some(code) = self.error_code {
+            write!(f, " {:?}", code)?;
+        } else if let Some(ref rpc_status) = self.rpc_status {
+            write!(f, " {}", rpc_status)?;
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        for violation in self.field_violations.iter() {
+            write!(f, "; {}: {}", violation.field, violation.description)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fatal errors. Referred from [Firebase
+/// documentation](https://firebase.google.com/docs/cloud-messaging/http-server-ref#table9)
+#[derive(Debug)]
+pub enum FcmError {
+    /// The sender account used to send a message couldn't be authenticated. Possible causes are:
+    ///
+    /// Authorization header missing or with invalid syntax in HTTP request.
+    ///
+    /// * The Firebase project that the specified server key belongs to is
+    ///   incorrect.
+    /// * Legacy server keys only—the request originated from a server not
+    ///   whitelisted in the Server key IPs.
+    ///
+    /// Check that the token you're sending inside the Authentication header is
+    /// the correct Server key associated with your project. See Checking the
+    /// validity of a Server key for details. If you are using a legacy server
+    /// key, you're recommended to upgrade to a new key that has no IP
+    /// restrictions.
+    ///
+    /// A 401 with `ErrorCode::ThirdPartyAuthError` is an `Api` error instead,
+    /// as it is about the APNs or web push credentials of the project.
+    Unauthorized(ApiError),
+
+    /// Check that the JSON message is properly formatted and contains valid
+    /// fields (for instance, making sure the right data type is passed in).
+    InvalidMessage(String),
+
+    /// The server couldn't process the request. Retry the same request, but you must:
+    ///
+    /// * Honor the [RetryAfter](enum.RetryAfter.html) value if included.
+    /// * Implement exponential back-off in your retry mechanism. (e.g. if you
+    ///   waited one second before the first retry, wait at least two second
+    ///   before the next one, then 4 seconds and so on). If you're sending
+    ///   multiple messages, delay each one independently by an additional random
+    ///   amount to avoid issuing a new request for all messages at the same time.
+    ///
+    /// Senders that cause problems risk being blacklisted.
+    ServerError {
+        retry_after: Option<RetryAfter>,
+        error: ApiError,
+    },
+
+    /// An access token could not be obtained: the service account key is
+    /// invalid, or the token endpoint refused the signed assertion. The
+    /// error it was caused by, if any, is kept as the `source()`.
+    Authentication {
+        context: String,
+        source: Option<Box<dyn Error + Send + Sync>>,
+    },
+
+    /// A setting given to the `ClientBuilder` is invalid.
+    InvalidConfiguration(String),
+
+    /// The HTTP client could not be set up with the given settings.
+    HttpClient(reqwest::Error),
+
+    /// FCM rejected the request. The error tells the HTTP status, the FCM
+    /// error code and which fields were invalid.
+    Api(ApiError),
+
+    /// The message could not be serialized to JSON, for example because its
+    /// data has a map with non-string keys.
+    Serialization(serde_json::Error),
+
+    /// FCM accepted the message, but its answer could not be read, for
+    /// example because a proxy replaced it.
+    InvalidResponse(serde_json::Error),
+
+    /// The request to FCM, or to a token endpoint, did not get an answer:
+    /// the connection failed or timed out, a name could not be resolved or
+    /// the TLS handshake failed.
+    Transport(reqwest::Error),
+
+    /// The sending limit was exceeded, for the project, a device or a topic
+    /// (HTTP 429). Retry no sooner than `retry_after` if FCM gave one, with
+    /// exponential back-off otherwise.
+    QuotaExceeded {
+        retry_after: Option<RetryAfter>,
+        error: ApiError,
+    },
+
+    /// The registration token is not valid anymore, for example because the
+    /// app was uninstalled (HTTP 404). `token` is the token the message was
+    /// sent to, if it was sent to one. Stop sending messages to it.
+    Unregistered { token: Option<String>, error: ApiError },
+
+    /// The registration token belongs to another sender than the project
+    /// sending the message (HTTP 403).
+    SenderIdMismatch(ApiError),
+
+    /// The message breaks constraints FCM documents, found by
+    /// `Message::validate` before sending it.
+    InvalidFields(Vec<FieldViolation>),
+}
+
+impl FcmError {
+    /// An authentication error caused by another error.
+    pub(crate) fn authentication<C, E>(context: C, source: E) -> FcmError
+    where
+        C: Into<String>,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        FcmError::Authentication {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// An authentication error that wasn't caused by another error.
+    pub(crate) fn authentication_failed<C: Into<String>>(context: C) -> FcmError {
+        FcmError::Authentication {
+            context: context.into(),
+            source: None,
+        }
+    }
+
+    /// Sort an error response of FCM into the matching variant. `token` is
+    /// the registration token the message was sent to, if any.
+    pub(crate) fn from_api_error(error: ApiError, retry_after: Option<RetryAfter>, token: Option<&str>) -> FcmError {
+        match (error.status, error.error_code) {
+            (_, Some(ErrorCode::ThirdPartyAuthError)) => FcmError::Api(error),
+            (401, _) => FcmError::Unauthorized(error),
+            (429, _) | (_, Some(ErrorCode::QuotaExceeded)) => FcmError::QuotaExceeded { retry_after, error },
+            (404, _) | (_, Some(ErrorCode::Unregistered)) => FcmError::Unregistered {
+                token: token.map(str::to_string),
+                error,
+            },
+            (403, _) | (_, Some(ErrorCode::SenderIdMismatch)) => FcmError::SenderIdMismatch(error),
+            (500..=599, _) => FcmError::ServerError { retry_after, error },
+            _ => FcmError::Api(error),
+        }
+    }
+
+    /// The error response of FCM, if the error came from one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            FcmError::Api(ref e)
+            | FcmError::Unauthorized(ref e)
+            | FcmError::ServerError { error: ref e, .. }
+            | FcmError::QuotaExceeded { error: ref e, .. }
+            | FcmError::Unregistered { error: ref e, .. }
+            | FcmError::SenderIdMismatch(ref e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status FCM answered with, if the error came from FCM.
+    pub fn status(&self) -> Option<u16> {
+        self.api_error().map(|e| e.status)
+    }
+
+    /// The FCM error code, if FCM answered with one.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        self.api_error().and_then(|e| e.error_code)
+    }
+
+    /// The error description given by FCM, if any.
+    pub fn api_message(&self) -> Option<&str> {
+        self.api_error().map(|e| e.message.as_str())
+    }
+
+    /// The fields of the request FCM rejected, or that were found invalid
+    /// before sending it.
+    pub fn field_violations(&self) -> &[FieldViolation] {
+        match self {
+            FcmError::InvalidFields(ref violations) => violations,
+            _ => match self.api_error() {
+                Some(e) => &e.field_violations,
+                None => &[],
+            },
+        }
+    }
+
+    /// How long FCM asked to wait before retrying, if it did.
+    pub fn retry_after(&self) -> Option<&RetryAfter> {
+        match self {
+            FcmError::ServerError { ref retry_after, .. } | FcmError::QuotaExceeded { ref retry_after, .. } => {
+                retry_after.as_ref()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Error for FcmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FcmError::HttpClient(ref e) | FcmError::Transport(ref e) => Some(e),
+            FcmError::Serialization(ref e) | FcmError::InvalidResponse(ref e) => Some(e),
+            FcmError::Authentication {
+                source: Some(ref source),
+                ..
+            } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FcmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FcmError::Unauthorized(ref error) => write!(f, "unauthorized: {}", error),
+            FcmError::InvalidMessage(ref s) => write!(f, "invalid message {}", s),
+            FcmError::ServerError { ref error, .. } => write!(f, "the server couldn't process the request: {}", error),
+            FcmError::Authentication {
+                ref context,
+                source: Some(ref source),
+            } => write!(f, "authentication failed: {}: {}", context, source),
+            FcmError::Authentication { ref context, .. } => write!(f, "authentication failed: {}", context),
+            FcmError::InvalidConfiguration(ref s) => write!(f, "invalid client configuration: {}", s),
+            FcmError::HttpClient(ref e) => write!(f, "cannot set up the HTTP client: {}", e),
+            FcmError::Api(ref e) => e.fmt(f),
+            FcmError::Serialization(ref e) => write!(f, "cannot serialize the message: {}", e),
+            FcmError::InvalidResponse(ref e) => write!(f, "cannot read the FCM response: {}", e),
+            FcmError::Transport(ref e) => write!(f, "request failed: {}", e),
+            FcmError::QuotaExceeded { ref error, .. } => write!(f, "quota exceeded: {}", error),
+            FcmError::Unregistered {
+                token: Some(ref token), ..
+            } => {
+                write!(f, "registration token {} is unregistered", token)
+            }
+            FcmError::Unregistered { ref error, .. } => write!(f, "unregistered: {}", error),
+            FcmError::SenderIdMismatch(ref error) => write!(f, "sender id mismatch: {}", error),
+            FcmError::InvalidFields(ref violations) => {
+                write!(f, "invalid message")?;
+
+                for (i, violation) in violations.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, violation)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for FcmError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Transport(error)
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum RetryAfter {
+    /// Amount of time to wait until retrying the message is allowed.
+    Delay(Duration),
+
+    /// A point in time until retrying the message is allowed.
+    DateTime(DateTime<FixedOffset>),
+}
+
+impl FromStr for RetryAfter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<i64>()
+            .map(Duration::seconds)
+            .map(RetryAfter::Delay)
+            .or_else(|_| DateTime::parse_from_rfc2822(s).map(RetryAfter::DateTime))
+            .map_err(|e| crate::Error::InvalidMessage(format!("{}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_send_response() {
+        let response: SendResponse =
+            serde_json::from_value(json!({"name": "projects/my-project/messages/0:123%abc"})).unwrap();
+
+        assert_eq!("my-project", response.name.project_id());
+        assert_eq!("0:123%abc", response.name.message_id());
+        assert_eq!("projects/my-project/messages/0:123%abc", response.name.to_string());
+    }
+
+    #[test]
+    fn test_reject_invalid_message_names() {
+        for name in [
+            "",
+            "projects/p",
+            "projects//messages/1",
+            "projects/p/messages/",
+            "topics/p/messages/1",
+        ]
+        .iter()
+        {
+            assert!(name.parse::<MessageName>().is_err(), "{} should not parse", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_fcm_error_body() {
+        let body = json!({
+            "error": {
+                "code": 400,
+                "message": "The registration token is not a valid FCM registration token",
+                "status": "INVALID_ARGUMENT",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "INVALID_ARGUMENT"
+                    },
+                    {
+                        "@type": "type.googleapis.com/google.rpc.BadRequest",
+                        "fieldViolations": [
+                            {
+                                "field": "message.token",
+                                "description": "Invalid registration token"
+                            }
+                        ]
+                    }
+                ]
+            }
+        });
+
+        let error = ApiError::from_response(400, body.to_string().as_bytes());
+
+        assert_eq!(
+            ApiError {
+                status: 400,
+                error_code: Some(ErrorCode::InvalidArgument),
+                rpc_status: Some("INVALID_ARGUMENT".to_string()),
+                message: "The registration token is not a valid FCM registration token".to_string(),
+                field_violations: vec![FieldViolation {
+                    field: "message.token".to_string(),
+                    description: "Invalid registration token".to_string(),
+                }],
+            },
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_error_codes() {
+        let codes = vec![
+            ("UNSPECIFIED_ERROR", ErrorCode::UnspecifiedError),
+            ("INVALID_ARGUMENT", ErrorCode::InvalidArgument),
+            ("UNREGISTERED", ErrorCode::Unregistered),
+            ("SENDER_ID_MISMATCH", ErrorCode::SenderIdMismatch),
+            ("QUOTA_EXCEEDED", ErrorCode::QuotaExceeded),
+            ("UNAVAILABLE", ErrorCode::Unavailable),
+            ("INTERNAL", ErrorCode::Internal),
+            ("THIRD_PARTY_AUTH_ERROR", ErrorCode::ThirdPartyAuthError),
+            ("APNS_AUTH_ERROR", ErrorCode::Unknown),
+        ];
+
+        for (code_str, code) in codes.into_iter() {
+            let body = json!({
+                "error": {
+                    "code": 404,
+                    "message": "",
+                    "details": [
+                        {"@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError", "errorCode": code_str}
+                    ]
+                }
+            });
+
+            let error = FcmError::Api(ApiError::from_response(404, body.to_string().as_bytes()));
+
+            assert_eq!(Some(code), error.error_code());
+            assert_eq!(Some(404), error.status());
+        }
+    }
+
+    #[test]
+    fn test_classify_api_errors() {
+        let error = |status: u16, code: Option<ErrorCode>| ApiError {
+            status,
+            error_code: code,
+            rpc_status: None,
+            message: String::new(),
+            field_violations: Vec::new(),
+        };
+
+        let quota = FcmError::from_api_error(
+            error(429, None),
+            Some(RetryAfter::Delay(Duration::seconds(10))),
+            Some("device"),
+        );
+        assert!(matches!(
+            quota,
+            FcmError::QuotaExceeded {
+                retry_after: Some(_),
+                ..
+            }
+        ));
+        assert_eq!(Some(&RetryAfter::Delay(Duration::seconds(10))), quota.retry_after());
+
+        match FcmError::from_api_error(error(404, Some(ErrorCode::Unregistered)), None, Some("device")) {
+            FcmError::Unregistered { token, .. } => assert_eq!(Some("device".to_string()), token),
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        assert!(matches!(
+            FcmError::from_api_error(error(403, Some(ErrorCode::SenderIdMismatch)), None, None),
+            FcmError::SenderIdMismatch(_)
+        ));
+        assert!(matches!(
+            FcmError::from_api_error(error(403, None), None, None),
+            FcmError::SenderIdMismatch(_)
+        ));
+        assert!(matches!(
+            FcmError::from_api_error(error(400, Some(ErrorCode::InvalidArgument)), None, None),
+            FcmError::Api(_)
+        ));
+    }
+
+    #[test]
+    fn test_keep_unparseable_error_body() {
+        let error = ApiError::from_response(502, b"<html>Bad Gateway</html>");
+
+        assert_eq!(None, error.error_code);
+        assert_eq!("<html>Bad Gateway</html>", error.message);
+    }
+
+    #[test]
+    fn test_retry_after_from_seconds() {
+        assert_eq!(RetryAfter::Delay(Duration::seconds(420)), "420".parse().unwrap());
+    }
+
+    #[test]
+    fn test_retry_after_from_date() {
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        let retry_after = RetryAfter::from_str(date).unwrap();
+
+        assert_eq!(
+            RetryAfter::DateTime(DateTime::parse_from_rfc2822(date).unwrap()),
+            retry_after,
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+
+use crate::client::response::{ErrorCode, FcmError, RetryAfter};
+
+/// A source of the current time and of delays, so that retries can be
+/// tested without waiting.
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// A future completing after the given duration.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// The wall clock, sleeping on the tokio timer.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// How a `Client` retries messages FCM could not process.
+///
+/// Only errors worth retrying are: 5xx answers, exceeded quotas, the
+/// `UNAVAILABLE` and `INTERNAL` error codes, and connections that failed or
+/// timed out. Between attempts the client
+/// waits exponentially longer, starting at the base delay, with random
+/// jitter, but at least as long as FCM asks for in `Retry-After`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fcm::{ClientBuilder, RetryPolicy};
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), fcm::Error> {
+/// let mut builder = ClientBuilder::new();
+/// builder.retry_policy(
+///     RetryPolicy::new()
+///         .max_attempts(5)
+///         .base_delay(Duration::from_millis(500))
+///         .deadline(Duration::from_secs(30)),
+/// );
+/// let client = builder.build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    deadline: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.2,
+            deadline: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Up to five attempts, starting with a one second delay, doubling up to
+    /// a minute, with 20% jitter and no deadline.
+    pub fn new() -> RetryPolicy {
+        Self::default()
+    }
+
+    /// How many times a message is sent at most, the first attempt
+    /// included.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The delay before the first retry, doubled for each further one.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// The longest backoff between two attempts. A longer `Retry-After` is
+    /// still honored.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// How much each backoff is randomly varied, as a fraction between 0.0
+    /// and 1.0 of the delay. Spreads retries of many messages apart.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Give up when the next attempt would start later than this after the
+    /// first one.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Take the time from and sleep on the given clock.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub(crate) async fn sleep(&self, duration: Duration) {
+        self.clock.sleep(duration).await
+    }
+
+    /// The delay before the next attempt, or `None` if the message should not
+    /// be retried. `attempt` is the number of the attempt that failed,
+    /// starting at 1, and `started` the time of the first attempt.
+    pub(crate) fn next_delay(&self, error: &FcmError, attempt: u32, started: DateTime<Utc>) -> Option<Duration> {
+        if attempt >= self.max_attempts || !is_retryable(error) {
+            return None;
+        }
+
+        let backoff = self.backoff(attempt);
+
+        let delay = match error.retry_after() {
+            Some(RetryAfter::Delay(delay)) => delay.to_std().unwrap_or_default().max(backoff),
+            Some(RetryAfter::DateTime(date_time)) => (date_time.with_timezone(&Utc) - self.now())
+                .to_std()
+                .unwrap_or_default()
+                .max(backoff),
+            None => backoff,
+        };
+
+        if let Some(deadline) = self.deadline {
+            let elapsed = (self.now() - started).to_std().unwrap_or_default();
+
+            if elapsed + delay > deadline {
+                return None;
+            }
+        }
+
+        Some(delay)
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .checked_mul(1 << (attempt - 1).min(31))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        if self.jitter == 0.0 {
+            return exponential;
+        }
+
+        let factor = 1.0 + self.jitter * rand::thread_rng().gen_range(-1.0..=1.0);
+
+        exponential.mul_f64(factor).min(self.max_delay)
+    }
+}
+
+fn is_retryable(error: &FcmError) -> bool {
+    match error {
+        FcmError::ServerError { .. } | FcmError::QuotaExceeded { .. } => true,
+        FcmError::Transport(ref e) => e.is_timeout() || e.is_connect(),
+        FcmError::Api(ref e) => {
+            e.status >= 500 || matches!(e.error_code, Some(ErrorCode::Unavailable) | Some(ErrorCode::Internal))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::response::ApiError;
+    use chrono::TimeZone;
+
+    fn api_error(status: u16, error_code: Option<ErrorCode>) -> ApiError {
+        ApiError {
+            status,
+            error_code,
+            rpc_status: None,
+            message: String::new(),
+            field_violations: Vec::new(),
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new().jitter(0.0)
+    }
+
+    /// A clock stopped at the given time.
+    struct Frozen(DateTime<Utc>);
+
+    impl Clock for Frozen {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+
+        fn sleep(&self, _: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(async {})
+        }
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = policy().max_delay(Duration::from_secs(5)).max_attempts(10);
+        let error = FcmError::ServerError {
+            retry_after: None,
+            error: api_error(503, None),
+        };
+        let started = Utc::now();
+
+        let delays: Vec<_> = (1..7)
+            .map(|attempt| policy.next_delay(&error, attempt, started))
+            .collect();
+
+        assert_eq!(
+            vec![1, 2, 4, 5, 5, 5]
+                .into_iter()
+                .map(|secs| Some(Duration::from_secs(secs)))
+                .collect::<Vec<_>>(),
+            delays
+        );
+    }
+
+    #[test]
+    fn test_stop_after_max_attempts() {
+        let policy = policy().max_attempts(3);
+        let error = FcmError::ServerError {
+            retry_after: None,
+            error: api_error(503, None),
+        };
+
+        assert!(policy.next_delay(&error, 2, Utc::now()).is_some());
+        assert!(policy.next_delay(&error, 3, Utc::now()).is_none());
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = RetryPolicy::new().jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = policy.backoff(2);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(3));
+        }
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        let started = Utc::now();
+
+        for error in [
+            FcmError::ServerError {
+                retry_after: None,
+                error: api_error(503, None),
+            },
+            FcmError::QuotaExceeded {
+                retry_after: None,
+                error: api_error(429, Some(ErrorCode::QuotaExceeded)),
+            },
+            FcmError::Api(api_error(503, None)),
+            FcmError::Api(api_error(400, Some(ErrorCode::Unavailable))),
+            FcmError::Api(api_error(400, Some(ErrorCode::Internal))),
+        ] {
+            assert!(policy().next_delay(&error, 1, started).is_some(), "{:?}", error);
+        }
+
+        for error in [
+            FcmError::Unauthorized(api_error(401, None)),
+            FcmError::Api(api_error(400, Some(ErrorCode::InvalidArgument))),
+            FcmError::Unregistered {
+                token: None,
+                error: api_error(404, Some(ErrorCode::Unregistered)),
+            },
+            FcmError::SenderIdMismatch(api_error(403, Some(ErrorCode::SenderIdMismatch))),
+        ] {
+            assert!(policy().next_delay(&error, 1, started).is_none(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_honor_retry_after_delay() {
+        let error = FcmError::ServerError {
+            retry_after: Some(RetryAfter::Delay(chrono::Duration::seconds(30))),
+            error: api_error(503, None),
+        };
+
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            policy().next_delay(&error, 1, Utc::now())
+        );
+    }
+
+    #[test]
+    fn test_honor_retry_after_date_time() {
+        let retry_after = DateTime::parse_from_rfc2822("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        let error = FcmError::ServerError {
+            retry_after: Some(RetryAfter::DateTime(retry_after)),
+            error: api_error(503, None),
+        };
+        let policy = policy().clock(Frozen(Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 0).unwrap()));
+
+        assert_eq!(
+            Some(Duration::from_secs(37)),
+            policy.next_delay(&error, 1, policy.now())
+        );
+    }
+
+    #[test]
+    fn test_give_up_past_the_deadline() {
+        let now = Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 0).unwrap();
+        let policy = policy().deadline(Duration::from_secs(10)).clock(Frozen(now));
+        let error = FcmError::ServerError {
+            retry_after: None,
+            error: api_error(503, None),
+        };
+        let started = now - chrono::Duration::seconds(9);
+
+        assert_eq!(Some(Duration::from_secs(1)), policy.next_delay(&error, 1, started));
+        assert_eq!(None, policy.next_delay(&error, 2, started));
+    }
+}
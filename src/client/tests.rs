@@ -0,0 +1,733 @@
+use crate::{Client, ClientBuilder, Message, ServiceAccountKey, StaticTokenProvider};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEY_JSON: &str = include_str!("../../tests/fixtures/service_account.json");
+
+async fn mount_token_endpoint(server: &MockServer, token: &str, expected_requests: u64) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": token,
+            "expires_in": 3600,
+            "token_type": "Bearer",
+        })))
+        .up_to_n_times(1)
+        .expect(expected_requests)
+        .mount(server)
+        .await;
+}
+
+fn service_account_client(server: &MockServer) -> Client {
+    let key = ServiceAccountKey::from_json(KEY_JSON)
+        .unwrap()
+        .with_token_uri(format!("{}/token", server.uri()));
+
+    let mut builder = Client::builder();
+    builder
+        .project_id("test-project")
+        .service_account_key(key)
+        .base_url(server.uri());
+    builder.build().unwrap()
+}
+
+#[tokio::test]
+async fn should_attach_service_account_token() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, "ya29.first", 1).await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/test-project/messages:send"))
+        .and(header("authorization", "Bearer ya29.first"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = service_account_client(&server);
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn should_retry_once_with_a_fresh_token_on_unauthorized() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, "ya29.first", 1).await;
+    mount_token_endpoint(&server, "ya29.second", 1).await;
+
+    Mock::given(method("POST"))
+        .and(header("authorization", "Bearer ya29.first"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(header("authorization", "Bearer ya29.second"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = service_account_client(&server);
+
+    let response = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!("test-project", response.name.project_id());
+    assert_eq!("1", response.name.message_id());
+}
+
+#[tokio::test]
+async fn should_not_retry_a_static_token() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut builder = Client::builder();
+    builder
+        .project_id("test-project")
+        .token_provider(StaticTokenProvider::new("static"))
+        .base_url(server.uri());
+    let client = builder.build().unwrap();
+
+    let result = client.send(Message::to_token("token").finalize().unwrap()).await;
+
+    assert!(matches!(result, Err(crate::Error::Unauthorized(_))));
+}
+
+#[tokio::test]
+async fn should_consult_a_custom_token_provider() {
+    use crate::{AccessToken, TokenProvider};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counting(AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl TokenProvider for Counting {
+        async fn token(&self, _: &reqwest::Client) -> Result<AccessToken, crate::Error> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(AccessToken::new(format!("custom-{}", n)))
+        }
+    }
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(header("authorization", "Bearer custom-0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let provider = Arc::new(Counting(AtomicUsize::new(0)));
+    let mut builder = Client::builder();
+    builder
+        .project_id("test-project")
+        .token_provider(provider.clone())
+        .base_url(server.uri());
+    let client = builder.build().unwrap();
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(1, provider.0.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn should_use_the_project_of_the_credentials() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, "ya29.first", 1).await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/fcm-test-project/messages:send"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/fcm-test-project/messages/1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let key = ServiceAccountKey::from_json(KEY_JSON)
+        .unwrap()
+        .with_token_uri(format!("{}/token", server.uri()));
+
+    let mut builder = ClientBuilder::new();
+    builder.service_account_key(key).base_url(server.uri());
+    let client = builder.build().unwrap();
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+}
+
+fn static_token_builder(server: &MockServer) -> ClientBuilder {
+    let mut builder = Client::builder();
+    builder
+        .project_id("test-project")
+        .token_provider(StaticTokenProvider::new("static"))
+        .base_url(format!("{}/", server.uri()));
+    builder
+}
+
+#[tokio::test]
+async fn should_send_default_headers() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/test-project/messages:send"))
+        .and(header("x-goog-user-project", "billing-project"))
+        .and(header("user-agent", "test-agent/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut builder = static_token_builder(&server);
+    builder
+        .default_header("x-goog-user-project", "billing-project")
+        .user_agent("test-agent/1.0");
+    let client = builder.build().unwrap();
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn should_use_an_injected_http_client() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(header("user-agent", "injected"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let http_client = reqwest::Client::builder().user_agent("injected").build().unwrap();
+
+    let mut builder = static_token_builder(&server);
+    builder.http_client(http_client);
+    let client = builder.build().unwrap();
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn should_time_out() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let mut builder = static_token_builder(&server);
+    builder.timeout(Duration::from_millis(100));
+    let client = builder.build().unwrap();
+
+    match client.send(Message::to_token("token").finalize().unwrap()).await {
+        Err(crate::Error::Transport(e)) => assert!(e.is_timeout()),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn should_report_unreachable_servers() {
+    let mut builder = Client::builder();
+    builder
+        .token_provider(StaticTokenProvider::new("static"))
+        .project_id("test-project")
+        .base_url("http://127.0.0.1:1");
+    let client = builder.build().unwrap();
+
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, crate::Error::Transport(_)));
+    assert!(std::error::Error::source(&error).is_some());
+}
+
+#[tokio::test]
+async fn should_report_unreadable_responses() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Welcome to the proxy</html>"))
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, crate::Error::InvalidResponse(_)));
+    assert!(std::error::Error::source(&error).is_some());
+}
+
+#[tokio::test]
+async fn should_not_send_invalid_messages() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let builder = Message::to_topic("breaking news");
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client.send(builder.finalize().unwrap()).await.unwrap_err();
+
+    assert!(matches!(error, crate::Error::InvalidFields(_)));
+    assert_eq!("message.topic", error.field_violations()[0].field);
+}
+
+#[test]
+fn should_reject_invalid_settings() {
+    let mut builder = Client::builder();
+    builder
+        .token_provider(StaticTokenProvider::new("static"))
+        .base_url("not a url");
+    assert!(matches!(builder.build(), Err(crate::Error::InvalidConfiguration(_))));
+
+    let mut builder = Client::builder();
+    builder
+        .token_provider(StaticTokenProvider::new("static"))
+        .base_url("http://127.0.0.1:1")
+        .default_header("x-goog-user-project", "line\nbreak");
+    assert!(matches!(builder.build(), Err(crate::Error::InvalidConfiguration(_))));
+
+    let mut builder = Client::builder();
+    builder
+        .token_provider(StaticTokenProvider::new("static"))
+        .http_client(reqwest::Client::new())
+        .timeout(Duration::from_secs(1));
+    assert!(matches!(builder.build(), Err(crate::Error::InvalidConfiguration(_))));
+}
+
+#[tokio::test]
+async fn should_parse_error_responses() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": {
+                "code": 400,
+                "message": "Invalid value at 'message.android.ttl'",
+                "status": "INVALID_ARGUMENT",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "INVALID_ARGUMENT"
+                    },
+                    {
+                        "@type": "type.googleapis.com/google.rpc.BadRequest",
+                        "fieldViolations": [
+                            {"field": "message.android.ttl", "description": "Invalid duration"}
+                        ]
+                    }
+                ]
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert_eq!(Some(400), error.status());
+    assert_eq!(Some(crate::ErrorCode::InvalidArgument), error.error_code());
+    assert_eq!(Some("Invalid value at 'message.android.ttl'"), error.api_message());
+    assert_eq!("message.android.ttl", error.field_violations()[0].field);
+}
+
+#[tokio::test]
+async fn should_report_exceeded_quotas_with_the_retry_delay() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "30")
+                .set_body_json(json!({
+                    "error": {
+                        "code": 429,
+                        "message": "Quota exceeded",
+                        "status": "RESOURCE_EXHAUSTED",
+                        "details": [
+                            {
+                                "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                                "errorCode": "QUOTA_EXCEEDED"
+                            }
+                        ]
+                    }
+                })),
+        )
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    match error {
+        crate::Error::QuotaExceeded { retry_after, error } => {
+            assert_eq!(
+                Some(crate::RetryAfter::Delay(chrono::Duration::seconds(30))),
+                retry_after
+            );
+            assert_eq!(Some(crate::ErrorCode::QuotaExceeded), error.error_code);
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn should_report_the_unregistered_token() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "error": {
+                "code": 404,
+                "message": "Requested entity was not found.",
+                "status": "NOT_FOUND",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNREGISTERED"
+                    }
+                ]
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("stale-token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert_eq!(Some(404), error.status());
+    assert!(matches!(
+        error,
+        crate::Error::Unregistered { token: Some(ref token), .. } if token == "stale-token"
+    ));
+}
+
+#[tokio::test]
+async fn should_report_sender_id_mismatches() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "error": {
+                "code": 403,
+                "message": "SenderId mismatch",
+                "status": "PERMISSION_DENIED",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "SENDER_ID_MISMATCH"
+                    }
+                ]
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, crate::Error::SenderIdMismatch(_)));
+}
+
+#[tokio::test]
+async fn should_read_the_error_code_of_server_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(json!({
+            "error": {
+                "code": 503,
+                "message": "The service is currently unavailable.",
+                "status": "UNAVAILABLE",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNAVAILABLE"
+                    }
+                ]
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = static_token_builder(&server).build().unwrap();
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, crate::Error::ServerError { .. }));
+    assert_eq!(Some(crate::ErrorCode::Unavailable), error.error_code());
+    assert_eq!(Some(503), error.status());
+    assert_eq!(Some("The service is currently unavailable."), error.api_message());
+}
+
+#[tokio::test]
+async fn should_not_refresh_the_token_on_third_party_auth_errors() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, "ya29.first", 1).await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/test-project/messages:send"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "code": 401,
+                "message": "Auth error from APNS or Web Push Service",
+                "status": "UNAUTHENTICATED",
+                "details": [
+                    {
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "THIRD_PARTY_AUTH_ERROR"
+                    }
+                ]
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = service_account_client(&server);
+    let error = client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, crate::Error::Api(_)));
+    assert_eq!(Some(crate::ErrorCode::ThirdPartyAuthError), error.error_code());
+    assert_eq!(Some(401), error.status());
+}
+
+#[derive(Clone, Default)]
+struct FakeClock {
+    slept: std::sync::Arc<std::sync::Mutex<Vec<Duration>>>,
+}
+
+impl crate::Clock for FakeClock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        let slept: Duration = self.slept.lock().unwrap().iter().sum();
+        chrono::Utc::now() + chrono::Duration::from_std(slept).unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+        self.slept.lock().unwrap().push(duration);
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test]
+async fn should_retry_server_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "7"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let clock = FakeClock::default();
+
+    let mut builder = static_token_builder(&server);
+    builder.retry_policy(crate::RetryPolicy::new().jitter(0.0).clock(clock.clone()));
+    let client = builder.build().unwrap();
+
+    client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(7)],
+        *clock.slept.lock().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn should_give_up_after_max_attempts() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let clock = FakeClock::default();
+
+    let mut builder = static_token_builder(&server);
+    builder.retry_policy(crate::RetryPolicy::new().max_attempts(3).clock(clock.clone()));
+    let client = builder.build().unwrap();
+
+    let result = client.send(Message::to_token("token").finalize().unwrap()).await;
+
+    assert!(matches!(
+        result,
+        Err(crate::Error::ServerError { retry_after: None, .. })
+    ));
+    assert_eq!(2, clock.slept.lock().unwrap().len());
+}
+
+#[tokio::test]
+async fn should_not_retry_invalid_messages() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let clock = FakeClock::default();
+
+    let mut builder = static_token_builder(&server);
+    builder.retry_policy(crate::RetryPolicy::new().clock(clock.clone()));
+    let client = builder.build().unwrap();
+
+    assert!(client
+        .send(Message::to_token("token").finalize().unwrap())
+        .await
+        .is_err());
+    assert!(clock.slept.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn should_send_a_shared_message_from_spawned_tasks() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/test-project/messages:send"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let token = String::from("token");
+    let message: Arc<Message<'static>> = Arc::new(Message::to_token(token).finalize().unwrap());
+    let client = Arc::new(static_token_builder(&server).build().unwrap());
+
+    client.send(&*message).await.unwrap();
+
+    let tasks: Vec<_> = (0..2)
+        .map(|_| {
+            let client = Arc::clone(&client);
+            let message = Arc::clone(&message);
+
+            tokio::spawn(async move { client.send(message).await })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn should_send_a_cloned_message() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/projects/test-project/messages:send"))
+        .and(body_json(json!({"message": {"token": "token"}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "projects/test-project/messages/1"
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let template: Message<'static> = Message::to_token(String::from("token")).finalize().unwrap();
+    let client = Arc::new(static_token_builder(&server).build().unwrap());
+
+    let message = template.clone();
+    let task = {
+        let client = Arc::clone(&client);
+        tokio::spawn(async move { client.send(message).await })
+    };
+
+    task.await.unwrap().unwrap();
+    client.send(&template).await.unwrap();
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::Mutex;
+
+use crate::client::provider::TokenProvider;
+use crate::client::response::FcmError;
+
+/// How long before its expiry a cached token is replaced by a fresh one, so
+/// that a token never runs out while a request is in flight.
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// An OAuth2 access token, together with the moment it stops being valid.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AccessToken {
+    /// The bearer token attached to requests.
+    pub token: String,
+
+    /// When the token expires, if known. A token without an expiry is
+    /// considered valid forever.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AccessToken {
+    /// A token that never expires.
+    pub fn new<S: Into<String>>(token: S) -> AccessToken {
+        AccessToken {
+            token: token.into(),
+            expires_at: None,
+        }
+    }
+
+    /// A token that expires `expires_in` from now.
+    pub fn expiring_in<S: Into<String>>(token: S, expires_in: Duration) -> AccessToken {
+        AccessToken {
+            token: token.into(),
+            expires_at: Some(Utc::now() + expires_in),
+        }
+    }
+
+    /// Whether the token expires within the given duration from now.
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        self.expires_at
+            .map(|expires_at| expires_at - Utc::now() <= duration)
+            .unwrap_or(false)
+    }
+}
+
+/// Hands out access tokens, caching them until shortly before they expire.
+///
+/// The cache is guarded by an async mutex which is held while a new token is
+/// fetched, so concurrent callers wait for a single refresh instead of each
+/// starting their own.
+pub(crate) struct TokenManager {
+    provider: Box<dyn TokenProvider>,
+    cached: Mutex<Option<AccessToken>>,
+}
+
+impl TokenManager {
+    pub(crate) fn new(provider: Box<dyn TokenProvider>) -> TokenManager {
+        TokenManager {
+            provider,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Whether fetching again can yield a different token than the one
+    /// already handed out.
+    pub(crate) fn can_refresh(&self) -> bool {
+        self.provider.can_refresh()
+    }
+
+    /// A token valid for at least the refresh margin, fetched only if the
+    /// cached one is missing or about to expire.
+    pub(crate) async fn token(&self, http_client: &reqwest::Client) -> Result<String, FcmError> {
+        let mut cached = self.cached.lock().await;
+
+        match *cached {
+            Some(ref token) if !token.expires_within(Duration::seconds(REFRESH_MARGIN_SECS)) => Ok(token.token.clone()),
+            _ => {
+                let token = self.provider.token(http_client).await?;
+                let value = token.token.clone();
+                *cached = Some(token);
+
+                Ok(value)
+            }
+        }
+    }
+
+    /// The project the provider's credentials belong to, if it knows.
+    pub(crate) async fn project_id(&self, http_client: &reqwest::Client) -> Result<Option<String>, FcmError> {
+        self.provider.project_id(http_client).await
+    }
+
+    /// Drop the cached token if it is the one that was rejected, so that the
+    /// next call to `token` fetches a new one. A token that was already
+    /// replaced by another caller is left alone.
+    pub(crate) async fn invalidate(&self, rejected: &str) {
+        let mut cached = self.cached.lock().await;
+
+        if cached.as_ref().map(|token| token.token == rejected).unwrap_or(false) {
+            *cached = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::oauth::ServiceAccountKey;
+    use serde_json::json;
+    use std::sync::Arc;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const KEY_JSON: &str = include_str!("../../tests/fixtures/service_account.json");
+
+    async fn token_server(expires_in: i64, expected_requests: u64) -> MockServer {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.token",
+                "expires_in": expires_in,
+                "token_type": "Bearer",
+            })))
+            .expect(expected_requests)
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    fn manager(server: &MockServer) -> TokenManager {
+        let key = ServiceAccountKey::from_json(KEY_JSON)
+            .unwrap()
+            .with_token_uri(server.uri());
+
+        TokenManager::new(Box::new(key))
+    }
+
+    #[test]
+    fn test_expires_within() {
+        assert!(!AccessToken::new("token").expires_within(Duration::days(365)));
+        assert!(AccessToken::expiring_in("token", Duration::seconds(10)).expires_within(Duration::seconds(60)));
+        assert!(!AccessToken::expiring_in("token", Duration::seconds(3600)).expires_within(Duration::seconds(60)));
+    }
+
+    #[tokio::test]
+    async fn test_caches_token_until_expiry() {
+        let server = token_server(3600, 1).await;
+        let manager = manager(&server);
+        let http_client = reqwest::Client::new();
+
+        assert_eq!("ya29.token", manager.token(&http_client).await.unwrap());
+        assert_eq!("ya29.token", manager.token(&http_client).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_token_close_to_expiry() {
+        let server = token_server(REFRESH_MARGIN_SECS - 1, 2).await;
+        let manager = manager(&server);
+        let http_client = reqwest::Client::new();
+
+        manager.token(&http_client).await.unwrap();
+        manager.token(&http_client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refetches_invalidated_token() {
+        let server = token_server(3600, 2).await;
+        let manager = manager(&server);
+        let http_client = reqwest::Client::new();
+
+        manager.token(&http_client).await.unwrap();
+        manager.invalidate("some.other.token").await;
+        manager.token(&http_client).await.unwrap();
+        manager.invalidate("ya29.token").await;
+        manager.token(&http_client).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_deduplicates_concurrent_refreshes() {
+        let server = token_server(3600, 1).await;
+        let manager = Arc::new(manager(&server));
+        let http_client = reqwest::Client::new();
+
+        let handles: Vec<_> = (0..16)
+            .map(|_| {
+                let manager = manager.clone();
+                let http_client = http_client.clone();
+
+                tokio::spawn(async move { manager.token(&http_client).await })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!("ya29.token", handle.await.unwrap().unwrap());
+        }
+    }
+}
@@ -12,15 +12,15 @@
 //! # use std::collections::HashMap;
 //! # #[tokio::main]
 //! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-//! let client = fcm::Client::new();
+//! let client = fcm::Client::new("<project id>".to_string(), "<access token>".to_string())?;
 //!
 //! let mut map = HashMap::new();
 //! map.insert("message", "Howdy!");
 //!
-//! let mut builder = fcm::MessageBuilder::new("<FCM API Key>", "<registration id>");
-//! builder.data(&map);
+//! let mut builder = fcm::Message::to_token("<registration id>");
+//! builder.data(&map)?;
 //!
-//! let response = client.send(builder.finalize()).await?;
+//! let response = client.send(builder.finalize()?).await?;
 //! println!("Sent: {:?}", response);
 //! # Ok(())
 //! # }
@@ -42,17 +42,17 @@
 //! ```no_run
 //! # #[tokio::main]
 //! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-//! let client = fcm::Client::new();
+//! let client = fcm::Client::new("<project id>".to_string(), "<access token>".to_string())?;
 //!
 //! let mut notification_builder = fcm::NotificationBuilder::new();
 //! notification_builder.title("Hey!");
 //! notification_builder.body("Do you want to catch up later?");
 //!
 //! let notification = notification_builder.finalize();
-//! let mut message_builder = fcm::MessageBuilder::new("<FCM API Key>", "<registration id>");
+//! let mut message_builder = fcm::Message::to_token("<registration id>");
 //! message_builder.notification(notification);
 //!
-//! let response = client.send(message_builder.finalize()).await?;
+//! let response = client.send(message_builder.finalize()?).await?;
 //! println!("Sent: {:?}", response);
 //! # Ok(())
 //! # }
@@ -65,4 +65,6 @@ pub use crate::notification::*;
 mod client;
 pub use crate::client::*;
 
+pub use async_trait::async_trait;
+
 pub use crate::client::response::FcmError as Error;
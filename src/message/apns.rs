@@ -0,0 +1,924 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Utc};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::client::response::FcmError;
+use crate::message::Priority;
+use crate::notification::NotificationV1;
+
+/// APNs only accepts Live Activity pushes for topics with this suffix.
+const LIVE_ACTIVITY_TOPIC_SUFFIX: &str = ".push-type.liveactivity";
+
+/// How urgently APNs delivers a notification, sent as `apns-priority`.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub enum ApnsPriority {
+    /// Deliver right away.
+    #[serde(rename = "10")]
+    Immediate,
+
+    /// Deliver considering the power of the device. Required for background
+    /// notifications.
+    #[serde(rename = "5")]
+    ConservePower,
+
+    /// Prioritize the power of the device above all else.
+    #[serde(rename = "1")]
+    Low,
+}
+
+/// The type of a notification, sent as `apns-push-type`.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ApnsPushType {
+    Alert,
+    Background,
+    Location,
+    Voip,
+    Complication,
+    Fileprovider,
+    Mdm,
+    #[serde(rename = "liveactivity")]
+    LiveActivity,
+}
+
+/// What a Live Activity push does with the activity.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveActivityEvent {
+    Start,
+    Update,
+    End,
+}
+
+/// How a notification interrupts the user.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum InterruptionLevel {
+    Passive,
+    Active,
+    TimeSensitive,
+    Critical,
+}
+
+/// The sound played for a notification.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ApnsSound<'a> {
+    /// A sound file of the app, or `default`.
+    Named(Cow<'a, str>),
+
+    /// A critical alert sound, played even when the device is muted, at a
+    /// volume between 0.0 and 1.0. Needs an entitlement from Apple.
+    Critical { name: Cow<'a, str>, volume: f32 },
+}
+
+impl Serialize for ApnsSound<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ApnsSound::Named(name) => serializer.serialize_str(name),
+            ApnsSound::Critical { name, volume } => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("critical", &1)?;
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("volume", volume)?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// The content of an alert shown by iOS.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApnsAlert<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtitle: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    launch_image: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title_loc_key: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title_loc_args: Option<Vec<Cow<'a, str>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loc_key: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loc_args: Option<Vec<Cow<'a, str>>>,
+}
+
+impl<'a> ApnsAlert<'a> {
+    fn or(self, other: ApnsAlert<'a>) -> ApnsAlert<'a> {
+        ApnsAlert {
+            title: self.title.or(other.title),
+            subtitle: self.subtitle.or(other.subtitle),
+            body: self.body.or(other.body),
+            launch_image: self.launch_image.or(other.launch_image),
+            title_loc_key: self.title_loc_key.or(other.title_loc_key),
+            title_loc_args: self.title_loc_args.or(other.title_loc_args),
+            loc_key: self.loc_key.or(other.loc_key),
+            loc_args: self.loc_args.or(other.loc_args),
+        }
+    }
+}
+
+/// The `aps` dictionary Apple defines for notifications.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Aps<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alert: Option<ApnsAlert<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    badge: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sound: Option<ApnsSound<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_id: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_available: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mutable_content: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_content_id: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interruption_level: Option<InterruptionLevel>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relevance_score: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<LiveActivityEvent>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_state: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_unix_time")]
+    timestamp: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_unix_time")]
+    dismissal_date: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_unix_time")]
+    stale_date: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes_type: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<Value>,
+}
+
+impl<'a> Aps<'a> {
+    fn or(self, other: Aps<'a>) -> Aps<'a> {
+        let alert = match (self.alert, other.alert) {
+            (Some(alert), Some(other)) => Some(alert.or(other)),
+            (alert, other) => alert.or(other),
+        };
+
+        Aps {
+            alert,
+            badge: self.badge.or(other.badge),
+            sound: self.sound.or(other.sound),
+            thread_id: self.thread_id.or(other.thread_id),
+            category: self.category.or(other.category),
+            content_available: self.content_available.or(other.content_available),
+            mutable_content: self.mutable_content.or(other.mutable_content),
+            target_content_id: self.target_content_id.or(other.target_content_id),
+            interruption_level: self.interruption_level.or(other.interruption_level),
+            relevance_score: self.relevance_score.or(other.relevance_score),
+            event: self.event.or(other.event),
+            content_state: self.content_state.or(other.content_state),
+            timestamp: self.timestamp.or(other.timestamp),
+            dismissal_date: self.dismissal_date.or(other.dismissal_date),
+            stale_date: self.stale_date.or(other.stale_date),
+            attributes_type: self.attributes_type.or(other.attributes_type),
+            attributes: self.attributes.or(other.attributes),
+        }
+    }
+}
+
+/// The payload of an APNs notification: the `aps` dictionary and the
+/// custom keys of the app. Use the corresponding `ApnsPayloadBuilder` to get
+/// an instance.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct ApnsPayload<'a> {
+    aps: Aps<'a>,
+
+    #[serde(flatten)]
+    custom: Map<String, Value>,
+}
+
+impl<'a> ApnsPayload<'a> {
+    fn or(mut self, other: ApnsPayload<'a>) -> ApnsPayload<'a> {
+        for (key, value) in other.custom {
+            self.custom.entry(key).or_insert(value);
+        }
+
+        ApnsPayload {
+            aps: self.aps.or(other.aps),
+            custom: self.custom,
+        }
+    }
+}
+
+/// A builder to get an `ApnsPayload` instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{ApnsConfigBuilder, ApnsPayloadBuilder, ApnsSound, InterruptionLevel, Message};
+///
+/// let mut payload = ApnsPayloadBuilder::new();
+/// payload
+///     .title("Gate changed")
+///     .body("Your flight now departs from gate B12")
+///     .badge(1)
+///     .sound(ApnsSound::Named("default".into()))
+///     .interruption_level(InterruptionLevel::TimeSensitive);
+/// payload.custom_key("flight", &"LH 454")?;
+///
+/// let mut apns = ApnsConfigBuilder::new();
+/// apns.payload(payload.finalize());
+///
+/// let mut message = Message::to_token("<registration id>");
+/// message.apns(apns.finalize());
+/// # Ok::<(), fcm::Error>(())
+/// ```
+#[derive(Default, Debug)]
+pub struct ApnsPayloadBuilder<'a> {
+    alert: ApnsAlert<'a>,
+    payload: ApnsPayload<'a>,
+}
+
+impl<'a> ApnsPayloadBuilder<'a> {
+    /// Get a new `ApnsPayloadBuilder` instance.
+    pub fn new() -> ApnsPayloadBuilder<'a> {
+        Self::default()
+    }
+
+    /// Set the title of the alert.
+    pub fn title<S: Into<Cow<'a, str>>>(&mut self, title: S) -> &mut Self {
+        self.alert.title = Some(title.into());
+        self
+    }
+
+    /// Set the subtitle of the alert.
+    pub fn subtitle<S: Into<Cow<'a, str>>>(&mut self, subtitle: S) -> &mut Self {
+        self.alert.subtitle = Some(subtitle.into());
+        self
+    }
+
+    /// Set the body of the alert.
+    pub fn body<S: Into<Cow<'a, str>>>(&mut self, body: S) -> &mut Self {
+        self.alert.body = Some(body.into());
+        self
+    }
+
+    /// The image shown while the app launches from the alert.
+    pub fn launch_image<S: Into<Cow<'a, str>>>(&mut self, launch_image: S) -> &mut Self {
+        self.alert.launch_image = Some(launch_image.into());
+        self
+    }
+
+    /// Set the title key string for localization.
+    pub fn title_loc_key<S: Into<Cow<'a, str>>>(&mut self, title_loc_key: S) -> &mut Self {
+        self.alert.title_loc_key = Some(title_loc_key.into());
+        self
+    }
+
+    /// String value to replace format specifiers in the title string.
+    pub fn title_loc_args<S>(&mut self, title_loc_args: &[S]) -> &mut Self
+    where
+        S: Into<Cow<'a, str>> + Clone,
+    {
+        self.alert.title_loc_args = Some(title_loc_args.iter().cloned().map(Into::into).collect());
+        self
+    }
+
+    /// Set the body key string for localization.
+    pub fn loc_key<S: Into<Cow<'a, str>>>(&mut self, loc_key: S) -> &mut Self {
+        self.alert.loc_key = Some(loc_key.into());
+        self
+    }
+
+    /// String value to replace format specifiers in the body string.
+    pub fn loc_args<S>(&mut self, loc_args: &[S]) -> &mut Self
+    where
+        S: Into<Cow<'a, str>> + Clone,
+    {
+        self.alert.loc_args = Some(loc_args.iter().cloned().map(Into::into).collect());
+        self
+    }
+
+    /// The number shown on the app icon, 0 to remove it.
+    pub fn badge(&mut self, badge: u32) -> &mut Self {
+        self.payload.aps.badge = Some(badge);
+        self
+    }
+
+    /// The sound played for the notification.
+    pub fn sound(&mut self, sound: ApnsSound<'a>) -> &mut Self {
+        self.payload.aps.sound = Some(sound);
+        self
+    }
+
+    /// Group notifications with the same thread id together.
+    pub fn thread_id<S: Into<Cow<'a, str>>>(&mut self, thread_id: S) -> &mut Self {
+        self.payload.aps.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// The notification category of the app, deciding the actions shown.
+    pub fn category<S: Into<Cow<'a, str>>>(&mut self, category: S) -> &mut Self {
+        self.payload.aps.category = Some(category.into());
+        self
+    }
+
+    /// When `true`, the app is woken up in the background to fetch new
+    /// content.
+    pub fn content_available(&mut self, content_available: bool) -> &mut Self {
+        self.payload.aps.content_available = Some(u8::from(content_available));
+        self
+    }
+
+    /// When `true`, the notification service extension of the app can
+    /// modify the notification before it is shown.
+    pub fn mutable_content(&mut self, mutable_content: bool) -> &mut Self {
+        self.payload.aps.mutable_content = Some(u8::from(mutable_content));
+        self
+    }
+
+    /// The window of the app brought forward when the notification is
+    /// opened.
+    pub fn target_content_id<S: Into<Cow<'a, str>>>(&mut self, target_content_id: S) -> &mut Self {
+        self.payload.aps.target_content_id = Some(target_content_id.into());
+        self
+    }
+
+    /// How the notification interrupts the user.
+    pub fn interruption_level(&mut self, interruption_level: InterruptionLevel) -> &mut Self {
+        self.payload.aps.interruption_level = Some(interruption_level);
+        self
+    }
+
+    /// Which notification of the app is featured in the summary, between
+    /// 0.0 and 1.0.
+    pub fn relevance_score(&mut self, relevance_score: f64) -> &mut Self {
+        self.payload.aps.relevance_score = Some(relevance_score);
+        self
+    }
+
+    /// Start, update or end a Live Activity.
+    pub fn live_activity_event(&mut self, event: LiveActivityEvent) -> &mut Self {
+        self.payload.aps.event = Some(event);
+        self
+    }
+
+    /// The dynamic content of a Live Activity, anything that Serde can
+    /// serialize to the `ContentState` of the activity attributes.
+    pub fn content_state(&mut self, content_state: &dyn erased_serde::Serialize) -> Result<&mut Self, FcmError> {
+        self.payload.aps.content_state = Some(serde_json::to_value(content_state).map_err(FcmError::Serialization)?);
+        Ok(self)
+    }
+
+    /// When the content state was produced. iOS ignores updates older than
+    /// the one shown.
+    pub fn timestamp(&mut self, timestamp: DateTime<Utc>) -> &mut Self {
+        self.payload.aps.timestamp = Some(timestamp);
+        self
+    }
+
+    /// When an ended Live Activity is removed from the lock screen.
+    pub fn dismissal_date(&mut self, dismissal_date: DateTime<Utc>) -> &mut Self {
+        self.payload.aps.dismissal_date = Some(dismissal_date);
+        self
+    }
+
+    /// When the content of a Live Activity becomes outdated.
+    pub fn stale_date(&mut self, stale_date: DateTime<Utc>) -> &mut Self {
+        self.payload.aps.stale_date = Some(stale_date);
+        self
+    }
+
+    /// The static attributes of a Live Activity started remotely, with the
+    /// name of their `ActivityAttributes` type in the app.
+    pub fn attributes<S: Into<Cow<'a, str>>>(
+        &mut self,
+        attributes_type: S,
+        attributes: &dyn erased_serde::Serialize,
+    ) -> Result<&mut Self, FcmError> {
+        self.payload.aps.attributes = Some(serde_json::to_value(attributes).map_err(FcmError::Serialization)?);
+        self.payload.aps.attributes_type = Some(attributes_type.into());
+        Ok(self)
+    }
+
+    /// Add a custom key next to the `aps` dictionary. The value can be
+    /// anything that Serde can serialize to JSON. The `aps` key itself is
+    /// reserved.
+    pub fn custom_key(&mut self, key: &str, value: &dyn erased_serde::Serialize) -> Result<&mut Self, FcmError> {
+        if key == "aps" {
+            return Err(FcmError::InvalidMessage(
+                "aps is reserved and can't be a custom key".to_string(),
+            ));
+        }
+
+        let value = serde_json::to_value(value).map_err(FcmError::Serialization)?;
+        self.payload.custom.insert(key.to_string(), value);
+        Ok(self)
+    }
+
+    /// Complete the build and get an `ApnsPayload` instance.
+    pub fn finalize(mut self) -> ApnsPayload<'a> {
+        if self.alert != ApnsAlert::default() {
+            self.payload.aps.alert = Some(self.alert);
+        }
+
+        self.payload
+    }
+}
+
+/// The APNs headers FCM passes on to Apple.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct ApnsHeaders<'a> {
+    #[serde(rename = "apns-priority", skip_serializing_if = "Option::is_none")]
+    priority: Option<ApnsPriority>,
+
+    #[serde(
+        rename = "apns-expiration",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_expiration"
+    )]
+    expiration: Option<DateTime<Utc>>,
+
+    #[serde(rename = "apns-push-type", skip_serializing_if = "Option::is_none")]
+    push_type: Option<ApnsPushType>,
+
+    #[serde(rename = "apns-collapse-id", skip_serializing_if = "Option::is_none")]
+    collapse_id: Option<Cow<'a, str>>,
+
+    #[serde(rename = "apns-topic", skip_serializing_if = "Option::is_none")]
+    topic: Option<Cow<'a, str>>,
+}
+
+impl<'a> ApnsHeaders<'a> {
+    fn or(self, other: ApnsHeaders<'a>) -> ApnsHeaders<'a> {
+        ApnsHeaders {
+            priority: self.priority.or(other.priority),
+            expiration: self.expiration.or(other.expiration),
+            push_type: self.push_type.or(other.push_type),
+            collapse_id: self.collapse_id.or(other.collapse_id),
+            topic: self.topic.or(other.topic),
+        }
+    }
+}
+
+/// APNs expects the expiration as a UNIX timestamp in a string.
+fn serialize_expiration<S: Serializer>(expiration: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match expiration {
+        Some(expiration) => serializer.serialize_str(&expiration.timestamp().max(0).to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Dates in the `aps` dictionary are UNIX timestamps.
+fn serialize_unix_time<S: Serializer>(date_time: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match date_time {
+        Some(date_time) => serializer.serialize_i64(date_time.timestamp()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Options for features provided by the FCM SDK for iOS.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct ApnsFcmOptions<'a> {
+    /// The label associated with the message's analytics data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analytics_label: Option<Cow<'a, str>>,
+
+    /// The URL of an image shown in the notification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Cow<'a, str>>,
+}
+
+/// The APNs specific settings of a message. Use the corresponding
+/// `ApnsConfigBuilder` to get an instance.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct ApnsConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) headers: Option<ApnsHeaders<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) payload: Option<ApnsPayload<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fcm_options: Option<ApnsFcmOptions<'a>>,
+
+    /// The APNs token of a Live Activity to start or update.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    live_activity_token: Option<Cow<'a, str>>,
+}
+
+impl<'a> ApnsConfig<'a> {
+    /// The iOS specific settings of a legacy message.
+    pub(crate) fn from_legacy(
+        notification: Option<&NotificationV1<'a>>,
+        priority: Option<&Priority>,
+        content_available: Option<bool>,
+        mutable_content: Option<bool>,
+    ) -> Result<ApnsConfig<'a>, FcmError> {
+        let mut aps = Aps::default();
+
+        if let Some(notification) = notification {
+            let alert = ApnsAlert {
+                title_loc_key: notification.title_loc_key.clone(),
+                title_loc_args: notification.title_loc_args.clone(),
+                loc_key: notification.body_loc_key.clone(),
+                loc_args: notification.body_loc_args.clone(),
+                ..ApnsAlert::default()
+            };
+
+            if alert != ApnsAlert::default() {
+                aps.alert = Some(alert);
+            }
+
+            if let Some(ref badge) = notification.badge {
+                let badge = badge
+                    .parse()
+                    .map_err(|_| FcmError::InvalidMessage(format!("badge must be a number, got {}", badge)))?;
+
+                aps.badge = Some(badge);
+            }
+
+            aps.sound = notification.sound.clone().map(ApnsSound::Named);
+            aps.category = notification.click_action.clone();
+        }
+
+        if content_available == Some(true) {
+            aps.content_available = Some(1);
+        }
+
+        if mutable_content == Some(true) {
+            aps.mutable_content = Some(1);
+        }
+
+        let headers = priority.map(|priority| ApnsHeaders {
+            priority: Some(match priority {
+                Priority::High => ApnsPriority::Immediate,
+                Priority::Normal => ApnsPriority::ConservePower,
+            }),
+            ..ApnsHeaders::default()
+        });
+
+        Ok(ApnsConfig {
+            headers,
+            payload: if aps == Aps::default() {
+                None
+            } else {
+                Some(ApnsPayload {
+                    aps,
+                    custom: Map::new(),
+                })
+            },
+            fcm_options: None,
+            live_activity_token: None,
+        })
+    }
+
+    /// The label associated with the analytics data of the message on iOS.
+    pub(crate) fn analytics_label(&self) -> Option<&str> {
+        self.fcm_options
+            .as_ref()
+            .and_then(|options| options.analytics_label.as_deref())
+    }
+
+    /// The URL of the image shown in the notification on iOS.
+    pub(crate) fn image(&self) -> Option<&str> {
+        self.fcm_options.as_ref().and_then(|options| options.image.as_deref())
+    }
+
+    /// Fill the settings not made in this config from another one.
+    pub(crate) fn or(self, other: ApnsConfig<'a>) -> ApnsConfig<'a> {
+        let headers = match (self.headers, other.headers) {
+            (Some(headers), Some(other)) => Some(headers.or(other)),
+            (headers, other) => headers.or(other),
+        };
+
+        let payload = match (self.payload, other.payload) {
+            (Some(payload), Some(other)) => Some(payload.or(other)),
+            (payload, other) => payload.or(other),
+        };
+
+        ApnsConfig {
+            headers,
+            payload,
+            fcm_options: self.fcm_options.or(other.fcm_options),
+            live_activity_token: self.live_activity_token.or(other.live_activity_token),
+        }
+    }
+}
+
+/// A builder to get an `ApnsConfig` instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use chrono::{Duration, Utc};
+/// use fcm::{ApnsConfigBuilder, ApnsPriority, ApnsPushType};
+///
+/// let mut builder = ApnsConfigBuilder::new();
+/// builder
+///     .priority(ApnsPriority::Immediate)
+///     .push_type(ApnsPushType::Alert)
+///     .expiration(Utc::now() + Duration::hours(1))
+///     .collapse_id("gate-change");
+/// let apns = builder.finalize();
+/// ```
+#[derive(Default, Debug)]
+pub struct ApnsConfigBuilder<'a> {
+    headers: ApnsHeaders<'a>,
+    fcm_options: ApnsFcmOptions<'a>,
+    payload: Option<ApnsPayload<'a>>,
+    live_activity_token: Option<Cow<'a, str>>,
+}
+
+impl<'a> ApnsConfigBuilder<'a> {
+    /// Get a new `ApnsConfigBuilder` instance.
+    pub fn new() -> ApnsConfigBuilder<'a> {
+        Self::default()
+    }
+
+    /// How urgently the notification is delivered.
+    pub fn priority(&mut self, priority: ApnsPriority) -> &mut Self {
+        self.headers.priority = Some(priority);
+        self
+    }
+
+    /// Until when APNs keeps trying to deliver the notification. A time in
+    /// the past means only a single attempt is made.
+    pub fn expiration(&mut self, expiration: DateTime<Utc>) -> &mut Self {
+        self.headers.expiration = Some(expiration);
+        self
+    }
+
+    /// The type of the notification. Required for watchOS and recommended
+    /// for all others.
+    pub fn push_type(&mut self, push_type: ApnsPushType) -> &mut Self {
+        self.headers.push_type = Some(push_type);
+        self
+    }
+
+    /// Notifications with the same collapse id replace each other.
+    pub fn collapse_id<S: Into<Cow<'a, str>>>(&mut self, collapse_id: S) -> &mut Self {
+        self.headers.collapse_id = Some(collapse_id.into());
+        self
+    }
+
+    /// The topic of the notification, usually the bundle id of the app.
+    pub fn topic<S: Into<Cow<'a, str>>>(&mut self, topic: S) -> &mut Self {
+        self.headers.topic = Some(topic.into());
+        self
+    }
+
+    /// Update a Live Activity of the app with the given bundle id: sets the
+    /// `liveactivity` push type and the topic with its required
+    /// `.push-type.liveactivity` suffix. Send the message to the push token
+    /// of the activity, or set `live_activity_token`.
+    pub fn live_activity<S: Into<Cow<'a, str>>>(&mut self, bundle_id: S) -> &mut Self {
+        let bundle_id = bundle_id.into();
+        let topic = if bundle_id.ends_with(LIVE_ACTIVITY_TOPIC_SUFFIX) {
+            bundle_id
+        } else {
+            Cow::Owned(format!("{}{}", bundle_id, LIVE_ACTIVITY_TOPIC_SUFFIX))
+        };
+
+        self.headers.push_type = Some(ApnsPushType::LiveActivity);
+        self.headers.topic = Some(topic);
+        self
+    }
+
+    /// The APNs token of a Live Activity, to start or update it alongside the
+    /// target of the message.
+    pub fn live_activity_token<S: Into<Cow<'a, str>>>(&mut self, live_activity_token: S) -> &mut Self {
+        self.live_activity_token = Some(live_activity_token.into());
+        self
+    }
+
+    /// The payload of the notification.
+    pub fn payload(&mut self, payload: ApnsPayload<'a>) -> &mut Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// The label associated with the analytics data of the message on iOS.
+    pub fn analytics_label<S: Into<Cow<'a, str>>>(&mut self, analytics_label: S) -> &mut Self {
+        self.fcm_options.analytics_label = Some(analytics_label.into());
+        self
+    }
+
+    /// The URL of an image shown in the notification. Overrides the image of
+    /// the shared notification.
+    pub fn image<S: Into<Cow<'a, str>>>(&mut self, image: S) -> &mut Self {
+        self.fcm_options.image = Some(image.into());
+        self
+    }
+
+    /// Complete the build and get an `ApnsConfig` instance.
+    pub fn finalize(self) -> ApnsConfig<'a> {
+        ApnsConfig {
+            headers: Some(self.headers).filter(|headers| *headers != ApnsHeaders::default()),
+            payload: self.payload,
+            fcm_options: Some(self.fcm_options).filter(|options| *options != ApnsFcmOptions::default()),
+            live_activity_token: self.live_activity_token,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_a_full_payload() {
+        let mut builder = ApnsPayloadBuilder::new();
+        builder
+            .title("Gate changed")
+            .subtitle("LH 454")
+            .body("Now departing from B12")
+            .launch_image("gate.png")
+            .title_loc_key("GATE_TITLE")
+            .title_loc_args(&["B12"])
+            .loc_key("GATE_BODY")
+            .loc_args(&["LH 454", "B12"])
+            .badge(2)
+            .sound(ApnsSound::Critical {
+                name: "alarm.caf".into(),
+                volume: 0.5,
+            })
+            .thread_id("flight-454")
+            .category("GATE_CHANGE")
+            .content_available(true)
+            .mutable_content(true)
+            .target_content_id("flight-454")
+            .interruption_level(InterruptionLevel::TimeSensitive)
+            .relevance_score(0.75);
+        builder.custom_key("flight", &json!({"number": 454})).unwrap();
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "alert": {
+                        "title": "Gate changed",
+                        "subtitle": "LH 454",
+                        "body": "Now departing from B12",
+                        "launch-image": "gate.png",
+                        "title-loc-key": "GATE_TITLE",
+                        "title-loc-args": ["B12"],
+                        "loc-key": "GATE_BODY",
+                        "loc-args": ["LH 454", "B12"]
+                    },
+                    "badge": 2,
+                    "sound": {"critical": 1, "name": "alarm.caf", "volume": 0.5},
+                    "thread-id": "flight-454",
+                    "category": "GATE_CHANGE",
+                    "content-available": 1,
+                    "mutable-content": 1,
+                    "target-content-id": "flight-454",
+                    "interruption-level": "time-sensitive",
+                    "relevance-score": 0.75
+                },
+                "flight": {"number": 454}
+            }),
+            serde_json::to_value(builder.finalize()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reject_aps_as_custom_key() {
+        assert!(ApnsPayloadBuilder::new().custom_key("aps", &1).is_err());
+    }
+
+    #[test]
+    fn test_serialize_headers() {
+        let mut builder = ApnsConfigBuilder::new();
+        builder
+            .priority(ApnsPriority::ConservePower)
+            .expiration(Utc.with_ymd_and_hms(2024, 6, 14, 19, 0, 0).unwrap())
+            .push_type(ApnsPushType::Background)
+            .collapse_id("scores")
+            .topic("com.example.scores");
+
+        assert_eq!(
+            json!({
+                "headers": {
+                    "apns-priority": "5",
+                    "apns-expiration": "1718391600",
+                    "apns-push-type": "background",
+                    "apns-collapse-id": "scores",
+                    "apns-topic": "com.example.scores"
+                }
+            }),
+            serde_json::to_value(builder.finalize()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_serialize_a_live_activity_update() {
+        let mut payload = ApnsPayloadBuilder::new();
+        payload
+            .live_activity_event(LiveActivityEvent::Update)
+            .timestamp(Utc.with_ymd_and_hms(2024, 6, 14, 19, 42, 0).unwrap())
+            .stale_date(Utc.with_ymd_and_hms(2024, 6, 14, 20, 0, 0).unwrap());
+        payload.content_state(&json!({"home": 2, "away": 1})).unwrap();
+
+        let mut builder = ApnsConfigBuilder::new();
+        builder
+            .live_activity("com.example.scores")
+            .live_activity_token("activity-token")
+            .payload(payload.finalize());
+
+        assert_eq!(
+            json!({
+                "headers": {
+                    "apns-push-type": "liveactivity",
+                    "apns-topic": "com.example.scores.push-type.liveactivity"
+                },
+                "payload": {
+                    "aps": {
+                        "event": "update",
+                        "content-state": {"home": 2, "away": 1},
+                        "timestamp": 1718394120,
+                        "stale-date": 1718395200
+                    }
+                },
+                "live_activity_token": "activity-token"
+            }),
+            serde_json::to_value(builder.finalize()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keep_an_existing_live_activity_topic_suffix() {
+        let mut builder = ApnsConfigBuilder::new();
+        builder.live_activity("com.example.scores.push-type.liveactivity");
+
+        assert_eq!(
+            Some("com.example.scores.push-type.liveactivity"),
+            builder.headers.topic.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_take_an_owned_live_activity_bundle_id() {
+        let bundle_id = String::from("com.example.scores");
+
+        let mut builder = ApnsConfigBuilder::new();
+        builder.live_activity(bundle_id);
+
+        assert_eq!(
+            Some("com.example.scores.push-type.liveactivity"),
+            builder.headers.topic.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_explicit_settings_win_over_legacy_ones() {
+        let legacy = ApnsConfig::from_legacy(None, Some(&Priority::Normal), Some(true), None).unwrap();
+
+        let mut payload = ApnsPayloadBuilder::new();
+        payload.badge(1);
+
+        let mut builder = ApnsConfigBuilder::new();
+        builder.priority(ApnsPriority::Immediate).payload(payload.finalize());
+
+        assert_eq!(
+            json!({
+                "headers": {"apns-priority": "10"},
+                "payload": {"aps": {"badge": 1, "content-available": 1}}
+            }),
+            serde_json::to_value(builder.finalize().or(legacy)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::client::response::FcmError;
+
+/// A color in the RGBA color space, a `google.type.Color`. Each component is
+/// a value between 0.0 and 1.0.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::Color;
+///
+/// let color: Color = "#ff8000".parse().unwrap();
+///
+/// assert_eq!(1.0, color.red);
+/// assert_eq!(0.0, color.blue);
+/// assert_eq!(None, color.alpha);
+/// ```
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct Color {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+
+    /// The opacity of the color, opaque if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f32>,
+}
+
+impl Color {
+    /// An opaque color from its red, green and blue components.
+    pub fn rgb(red: f32, green: f32, blue: f32) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha: None,
+        }
+    }
+
+    /// A color from a hex string in `#rrggbb` or `#rrggbbaa` format.
+    pub fn from_hex(hex: &str) -> Result<Color, FcmError> {
+        let invalid = || FcmError::InvalidMessage(format!("invalid color {}, expected #rrggbb or #rrggbbaa", hex));
+
+        let digits = hex.strip_prefix('#').ok_or_else(invalid)?;
+
+        if !(digits.len() == 6 || digits.len() == 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let component = |i: usize| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map(|value| f32::from(value) / 255.0)
+                .map_err(|_| invalid())
+        };
+
+        Ok(Color {
+            red: component(0)?,
+            green: component(2)?,
+            blue: component(4)?,
+            alpha: if digits.len() == 8 { Some(component(6)?) } else { None },
+        })
+    }
+}
+
+impl FromStr for Color {
+    type Err = FcmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::from_hex(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_hex_colors() {
+        assert_eq!(Color::rgb(1.0, 0.0, 0.2), "#ff0033".parse().unwrap());
+        assert_eq!(Some(0.0), Color::from_hex("#FFFFFF00").unwrap().alpha);
+    }
+
+    #[test]
+    fn test_reject_invalid_hex_colors() {
+        for hex in ["ff0033", "#ff003", "#ff00336", "#gg0033", "#ff0033ff00"].iter() {
+            assert!(Color::from_hex(hex).is_err(), "{} should not parse", hex);
+        }
+    }
+
+    #[test]
+    fn test_serialize_color() {
+        assert_eq!(
+            json!({"red": 1.0, "green": 0.0, "blue": 0.0, "alpha": 1.0}),
+            serde_json::to_value(Color::from_hex("#ff0000ff").unwrap()).unwrap()
+        );
+    }
+}
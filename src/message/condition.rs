@@ -0,0 +1,427 @@
+use std::borrow::{Borrow, Cow};
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::ops;
+use std::str::FromStr;
+
+use crate::client::response::{FcmError, FieldViolation};
+use crate::message::validate::validate_topic_name;
+use crate::message::MAX_TOPICS_IN_CONDITION;
+
+/// A condition on the topics a device is subscribed to, deciding which
+/// devices a message is sent to.
+///
+/// Build one from `topic`, `and`, `or` and `not`, or parse an existing
+/// condition. Its `Display` is the FCM syntax, with parentheses wherever
+/// `&&` and `||` are mixed, since FCM evaluates them from left to right.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{not, topic, Condition, Message};
+///
+/// let condition = topic("news").and(topic("sports").or(topic("weather"))).and(not(topic("muted")));
+/// assert_eq!(
+///     "'news' in topics && ('sports' in topics || 'weather' in topics) && !('muted' in topics)",
+///     condition.to_string()
+/// );
+///
+/// let parsed: Condition = "'news' in topics && ('sports' in topics || 'weather' in topics)".parse()?;
+/// assert!(parsed.matches(&["news", "weather"].iter().copied().collect()));
+///
+/// let builder = Message::to_condition(condition);
+/// # Ok::<(), fcm::Error>(())
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Condition {
+    /// Devices subscribed to the topic.
+    Topic(String),
+
+    /// Devices the condition doesn't match.
+    Not(Box<Condition>),
+
+    /// Devices both conditions match.
+    And(Box<Condition>, Box<Condition>),
+
+    /// Devices either condition matches.
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// Devices subscribed to the topic.
+pub fn topic<S: Into<String>>(name: S) -> Condition {
+    Condition::Topic(name.into())
+}
+
+/// Devices the condition doesn't match.
+pub fn not(condition: Condition) -> Condition {
+    Condition::Not(Box::new(condition))
+}
+
+impl Condition {
+    /// Devices this and the other condition match.
+    pub fn and(self, other: Condition) -> Condition {
+        Condition::And(Box::new(self), Box::new(other))
+    }
+
+    /// Devices this or the other condition matches.
+    pub fn or(self, other: Condition) -> Condition {
+        Condition::Or(Box::new(self), Box::new(other))
+    }
+
+    /// The topics named in the condition, in order, repeated if named more
+    /// than once.
+    pub fn topics(&self) -> Vec<&str> {
+        match self {
+            Condition::Topic(ref name) => vec![name.as_str()],
+            Condition::Not(ref condition) => condition.topics(),
+            Condition::And(ref left, ref right) | Condition::Or(ref left, ref right) => {
+                let mut topics = left.topics();
+                topics.extend(right.topics());
+                topics
+            }
+        }
+    }
+
+    /// Whether a device subscribed to the given topics would get the
+    /// message.
+    pub fn matches<S>(&self, subscribed: &HashSet<S>) -> bool
+    where
+        S: Borrow<str> + Eq + Hash,
+    {
+        match self {
+            Condition::Topic(ref name) => subscribed.contains(name.as_str()),
+            Condition::Not(ref condition) => !condition.matches(subscribed),
+            Condition::And(ref left, ref right) => left.matches(subscribed) && right.matches(subscribed),
+            Condition::Or(ref left, ref right) => left.matches(subscribed) || right.matches(subscribed),
+        }
+    }
+
+    /// Check the topic names and that at most five topics are named.
+    ///
+    /// Fails with `FcmError::InvalidFields`, listing every violation found.
+    pub fn validate(&self) -> Result<(), FcmError> {
+        let mut violations = Vec::new();
+        self.check("condition", &mut violations);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(FcmError::InvalidFields(violations))
+        }
+    }
+
+    pub(crate) fn check(&self, field: &str, violations: &mut Vec<FieldViolation>) {
+        let topics = self.topics();
+
+        if topics.len() > MAX_TOPICS_IN_CONDITION {
+            violations.push(FieldViolation::new(
+                field,
+                format!(
+                    "condition names {} topics, at most {} are allowed",
+                    topics.len(),
+                    MAX_TOPICS_IN_CONDITION
+                ),
+            ));
+        }
+
+        for topic in topics {
+            if let Err(description) = validate_topic_name(topic) {
+                violations.push(FieldViolation::new(field, description));
+            }
+        }
+    }
+
+    /// Parse a condition, describing what is wrong if it can't be.
+    pub(crate) fn parse(condition: &str) -> Result<Condition, String> {
+        let mut parser = Parser {
+            input: condition,
+            position: 0,
+        };
+
+        let parsed = parser.expression()?;
+        parser.skip_whitespace();
+
+        match parser.rest().chars().next() {
+            None => Ok(parsed),
+            Some(c) => Err(parser.error(&format!("unexpected {:?}", c))),
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent: &Condition) -> fmt::Result {
+        let needs_parentheses = matches!(
+            (parent, self),
+            (Condition::And(..), Condition::Or(..)) | (Condition::Or(..), Condition::And(..))
+        );
+
+        if needs_parentheses {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl ops::Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        not(self)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Topic(ref name) => {
+                // The syntax has no escapes, so quote with `"` when the name
+                // contains a `'`.
+                let quote = if name.contains('\'') { '"' } else { '\'' };
+                write!(f, "{}{}{} in topics", quote, name, quote)
+            }
+            Condition::Not(ref condition) => write!(f, "!({})", condition),
+            Condition::And(ref left, ref right) => {
+                left.fmt_operand(f, self)?;
+                write!(f, " && ")?;
+                right.fmt_operand(f, self)
+            }
+            Condition::Or(ref left, ref right) => {
+                left.fmt_operand(f, self)?;
+                write!(f, " || ")?;
+                right.fmt_operand(f, self)
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = FcmError;
+
+    /// Parse a condition in the FCM syntax, such as
+    /// `'TopicA' in topics && ('TopicB' in topics || 'TopicC' in topics)`.
+    /// Like FCM, `&&` and `||` are evaluated from left to right.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Condition::parse(s).map_err(FcmError::InvalidMessage)
+    }
+}
+
+impl From<Condition> for Cow<'_, str> {
+    fn from(condition: Condition) -> Self {
+        Cow::Owned(condition.to_string())
+    }
+}
+
+/// A recursive descent parser for conditions.
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn error(&self, description: &str) -> String {
+        format!(
+            "invalid condition {:?} at {}: {}",
+            self.input, self.position, description
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume the token if the input continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", token)))
+        }
+    }
+
+    /// Like `expect`, but the word can't run on into a letter, digit or `_`,
+    /// so `intopics` isn't read as `in topics`.
+    fn expect_word(&mut self, word: &str) -> Result<(), String> {
+        self.expect(word)?;
+
+        match self.rest().chars().next() {
+            Some(c) if c.is_alphanumeric() || c == '_' => Err(self.error(&format!("expected {}", word))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Operands joined by `&&` and `||`, grouped from left to right.
+    fn expression(&mut self) -> Result<Condition, String> {
+        let mut condition = self.operand()?;
+
+        loop {
+            if self.eat("&&") {
+                condition = condition.and(self.operand()?);
+            } else if self.eat("||") {
+                condition = condition.or(self.operand()?);
+            } else {
+                return Ok(condition);
+            }
+        }
+    }
+
+    /// A negation, a parenthesized expression or `'topic' in topics`.
+    fn operand(&mut self) -> Result<Condition, String> {
+        if self.eat("!") {
+            return Ok(not(self.operand()?));
+        }
+
+        if self.eat("(") {
+            let condition = self.expression()?;
+            self.expect(")")?;
+            return Ok(condition);
+        }
+
+        let name = self.quoted()?;
+        self.expect_word("in")?;
+        self.expect_word("topics")?;
+
+        Ok(topic(name))
+    }
+
+    fn quoted(&mut self) -> Result<&'a str, String> {
+        self.skip_whitespace();
+
+        let quote = match self.rest().chars().next() {
+            Some(quote @ '\'') | Some(quote @ '"') => quote,
+            _ => return Err(self.error("expected a quoted topic name")),
+        };
+
+        let rest = &self.rest()[1..];
+
+        match rest.find(quote) {
+            Some(end) => {
+                self.position += end + 2;
+                Ok(&rest[..end])
+            }
+            None => Err(self.error("unterminated topic name")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribed(topics: &[&'static str]) -> HashSet<&'static str> {
+        topics.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_display_with_parentheses_where_needed() {
+        assert_eq!("'a' in topics", topic("a").to_string());
+        assert_eq!(
+            "'a' in topics && 'b' in topics && 'c' in topics",
+            topic("a").and(topic("b").and(topic("c"))).to_string()
+        );
+        assert_eq!(
+            "('a' in topics || 'b' in topics) && !('c' in topics || 'd' in topics)",
+            topic("a").or(topic("b")).and(!topic("c").or(topic("d"))).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(topic("TopicA").and(topic("TopicB").or(topic("TopicC")))),
+            Condition::parse(r#"'TopicA' in topics && ("TopicB" in topics || 'TopicC' in topics)"#)
+        );
+        assert_eq!(
+            Ok(not(topic("a")).and(not(not(topic("b"))))),
+            Condition::parse("!('a' in topics)&&!!'b' in topics")
+        );
+    }
+
+    #[test]
+    fn test_parse_from_left_to_right() {
+        assert_eq!(
+            Ok(topic("a").or(topic("b")).and(topic("c"))),
+            Condition::parse("'a' in topics || 'b' in topics && 'c' in topics")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let condition = topic("a").or(topic("b").and(not(topic("c")))).or(topic("d"));
+
+        assert_eq!(Ok(condition.clone()), Condition::parse(&condition.to_string()));
+
+        let condition = topic("it's").and(topic("news"));
+        assert_eq!(r#""it's" in topics && 'news' in topics"#, condition.to_string());
+        assert_eq!(Ok(condition.clone()), Condition::parse(&condition.to_string()));
+    }
+
+    #[test]
+    fn test_reject_invalid_syntax() {
+        for condition in [
+            "",
+            "a in topics",
+            "'a' in topic",
+            "'a' in topics &&",
+            "('a' in topics",
+            "'a' in topics)",
+            "'a in topics",
+            "'a' in topics & 'b' in topics",
+            "'a' intopics",
+            "'a' in topicsx || 'b' in topics",
+            "'a' in_topics",
+        ] {
+            assert!(
+                matches!(condition.parse::<Condition>(), Err(FcmError::InvalidMessage(_))),
+                "{:?}",
+                condition
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(topic("news-2024").and(topic("sports")).validate().is_ok());
+
+        let error = topic("breaking news").validate().unwrap_err();
+        assert_eq!("condition", error.field_violations()[0].field);
+
+        let six_topics = ["a", "b", "c", "d", "e", "f"]
+            .iter()
+            .map(|name| topic(*name))
+            .reduce(Condition::or)
+            .unwrap();
+        assert!(matches!(six_topics.validate(), Err(FcmError::InvalidFields(_))));
+    }
+
+    #[test]
+    fn test_match_subscribed_topics() {
+        let condition = topic("news")
+            .and(topic("sports").or(topic("weather")))
+            .and(not(topic("muted")));
+
+        assert!(condition.matches(&subscribed(&["news", "sports"])));
+        assert!(condition.matches(&subscribed(&["news", "weather", "other"])));
+        assert!(!condition.matches(&subscribed(&["news"])));
+        assert!(!condition.matches(&subscribed(&["sports", "weather"])));
+        assert!(!condition.matches(&subscribed(&["news", "sports", "muted"])));
+
+        let owned: HashSet<String> = vec!["news".to_string(), "sports".to_string()].into_iter().collect();
+        assert!(condition.matches(&owned));
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::client::response::FcmError;
+
+/// The custom key/value payload of a message. FCM only accepts strings for
+/// both, so nothing else can be put in.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::DataPayload;
+///
+/// let mut data = DataPayload::new();
+/// data.insert("chat_id", "42").insert("sender", "Alice");
+///
+/// assert_eq!(Some("42"), data.get("chat_id"));
+/// ```
+#[derive(Serialize, Debug, PartialEq, Eq, Default, Clone)]
+#[serde(transparent)]
+pub struct DataPayload(BTreeMap<String, String>);
+
+impl DataPayload {
+    /// Get a new, empty `DataPayload` instance.
+    pub fn new() -> DataPayload {
+        Self::default()
+    }
+
+    /// Set the value of a key, replacing the previous one.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Self {
+        self.0.insert(key.into(), value.into());
+        self
+    }
+
+    /// The value of a key, if set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// The number of keys.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether no key is set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The keys and values, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Flatten anything that Serde serializes to a map, like a struct, into
+    /// a payload. Strings are taken as they are, numbers and booleans are
+    /// formatted, nested values are encoded as JSON and `None` fields are left
+    /// out.
+    ///
+    /// Fails if the value isn't serialized to a map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use fcm::{DataPayload, Error, ToFcmData};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Score {
+    ///     match_id: u32,
+    ///     scores: [u8; 2],
+    /// }
+    ///
+    /// impl ToFcmData for Score {
+    ///     fn to_fcm_data(&self) -> Result<DataPayload, Error> {
+    ///         DataPayload::from_serialize(self)
+    ///     }
+    /// }
+    ///
+    /// let data = Score { match_id: 42, scores: [2, 1] }.to_fcm_data()?;
+    ///
+    /// assert_eq!(Some("42"), data.get("match_id"));
+    /// assert_eq!(Some("[2,1]"), data.get("scores"));
+    /// # Ok::<(), fcm::Error>(())
+    /// ```
+    pub fn from_serialize<T: Serialize + ?Sized>(data: &T) -> Result<DataPayload, FcmError> {
+        match serde_json::to_value(data).map_err(FcmError::Serialization)? {
+            Value::Object(map) => Ok(Self::from_json_map(&map)),
+            other => Err(FcmError::InvalidMessage(format!(
+                "data must be a map of keys to values, got {}",
+                other
+            ))),
+        }
+    }
+
+    fn from_json_map(map: &Map<String, Value>) -> DataPayload {
+        map.iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    Value::Null => return None,
+                    Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                };
+
+                Some((key.clone(), value))
+            })
+            .collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for DataPayload {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        DataPayload(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Anything that can be sent as the data of a message.
+///
+/// Maps of strings to strings convert as they are. For a struct, implement
+/// it with `DataPayload::from_serialize`.
+pub trait ToFcmData {
+    /// The key/value payload to send.
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError>;
+}
+
+impl ToFcmData for DataPayload {
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError> {
+        Ok(self.clone())
+    }
+}
+
+impl<K, V, S> ToFcmData for HashMap<K, V, S>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+    S: BuildHasher,
+{
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError> {
+        Ok(self.iter().map(|(key, value)| (key.as_ref(), value.as_ref())).collect())
+    }
+}
+
+impl<K, V> ToFcmData for BTreeMap<K, V>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError> {
+        Ok(self.iter().map(|(key, value)| (key.as_ref(), value.as_ref())).collect())
+    }
+}
+
+/// Flattened like `DataPayload::from_serialize` does.
+impl ToFcmData for Map<String, Value> {
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError> {
+        Ok(DataPayload::from_json_map(self))
+    }
+}
+
+/// Flattened like `DataPayload::from_serialize` does.
+impl ToFcmData for Value {
+    fn to_fcm_data(&self) -> Result<DataPayload, FcmError> {
+        DataPayload::from_serialize(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Match<'a> {
+        id: u64,
+        live: bool,
+        venue: Option<&'a str>,
+        teams: [&'a str; 2],
+        referee: Option<&'a str>,
+    }
+
+    #[test]
+    fn test_flatten_a_struct() {
+        let data = DataPayload::from_serialize(&Match {
+            id: 42,
+            live: true,
+            venue: Some("Stadium"),
+            teams: ["Home", "Away"],
+            referee: None,
+        })
+        .unwrap();
+
+        assert_eq!(
+            json!({"id": "42", "live": "true", "venue": "Stadium", "teams": "[\"Home\",\"Away\"]"}),
+            serde_json::to_value(data).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_flatten_nested_json() {
+        let data = json!({"score": {"home": 2, "away": 1}, "minute": 90.5})
+            .to_fcm_data()
+            .unwrap();
+
+        assert_eq!(Some(r#"{"away":1,"home":2}"#), data.get("score"));
+        assert_eq!(Some("90.5"), data.get("minute"));
+    }
+
+    #[test]
+    fn test_reject_values_other_than_maps() {
+        for value in [json!("text"), json!(1), json!(["a", "b"]), json!(null)] {
+            assert!(
+                matches!(value.to_fcm_data(), Err(FcmError::InvalidMessage(_))),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_string_maps() {
+        let mut map = HashMap::new();
+        map.insert("message", "Howdy!".to_string());
+
+        assert_eq!(
+            vec![("message", "Howdy!")],
+            map.to_fcm_data().unwrap().iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use serde::Serializer;
+
+/// Format a duration like the JSON mapping of `google.protobuf.Duration`:
+/// seconds with up to nine fractional digits and an `s` suffix, such as
+/// `3s` or `3.5s`.
+pub(crate) fn format(duration: &Duration) -> String {
+    let nanos = duration.subsec_nanos();
+
+    if nanos == 0 {
+        return format!("{}s", duration.as_secs());
+    }
+
+    let fraction = format!("{:09}", nanos);
+
+    format!("{}.{}s", duration.as_secs(), fraction.trim_end_matches('0'))
+}
+
+/// Serialize a duration as a `google.protobuf.Duration`, for use with
+/// `#[serde(serialize_with)]`.
+pub(crate) fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(duration))
+}
+
+/// Serialize an optional duration as a `google.protobuf.Duration`.
+pub(crate) fn serialize_option<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(ref duration) => serialize(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serialize an optional list of durations as `google.protobuf.Duration`s.
+pub(crate) fn serialize_option_seq<S: Serializer>(
+    durations: &Option<Vec<Duration>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match durations {
+        Some(ref durations) => serializer.collect_seq(durations.iter().map(format)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_durations() {
+        assert_eq!("0s", format(&Duration::from_secs(0)));
+        assert_eq!("3s", format(&Duration::from_secs(3)));
+        assert_eq!("3.5s", format(&Duration::from_millis(3500)));
+        assert_eq!("0.25s", format(&Duration::from_millis(250)));
+        assert_eq!("3.000000001s", format(&Duration::new(3, 1)));
+    }
+}
@@ -1,48 +1,112 @@
 use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::Serialize;
-use serde_json::Value;
 
+use crate::client::response::FcmError;
 use crate::notification::NotificationV1;
 
+mod apns;
+pub use crate::message::apns::{
+    ApnsAlert, ApnsConfig, ApnsConfigBuilder, ApnsFcmOptions, ApnsHeaders, ApnsPayload, ApnsPayloadBuilder,
+    ApnsPriority, ApnsPushType, ApnsSound, Aps, InterruptionLevel, LiveActivityEvent,
+};
+mod color;
+pub use crate::message::color::Color;
+mod condition;
+pub use crate::message::condition::{not, topic, Condition};
+mod data;
+pub use crate::message::data::{DataPayload, ToFcmData};
+mod duration;
+mod timestamp;
+mod validate;
+pub use crate::message::validate::{MAX_PAYLOAD_SIZE, MAX_TOPICS_IN_CONDITION};
+mod webpush;
+pub use crate::message::webpush::{
+    WebpushConfig, WebpushConfigBuilder, WebpushDirection, WebpushFcmOptions, WebpushHeaders, WebpushNotification,
+    WebpushNotificationAction, WebpushNotificationBuilder, WebpushUrgency,
+};
+
 #[cfg(test)]
 mod tests;
 
-#[derive(Serialize, PartialEq, Debug)]
-#[serde(rename_all = "lowercase")]
+/// The longest time FCM keeps a message for an offline device, four weeks.
+pub const MAX_TIME_TO_LIVE: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// The delivery priority of a message on Android.
+#[derive(Serialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Priority {
+    /// Delivered when the device is awake, may be delayed to save battery.
     Normal,
+    /// Delivered right away, waking a sleeping device.
     High,
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-#[serde(rename_all = "lowercase")]
+/// The priority of a notification, deciding how intrusively it is shown.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationPriority {
+    /// No priority given, FCM uses `PriorityDefault`.
+    PriorityUnspecified,
+    /// May only be shown in special places, such as detailed notification logs.
+    PriorityMin,
+    /// May be shown smaller or lower in the list than `PriorityDefault`.
+    PriorityLow,
+    /// The priority of most notifications.
+    PriorityDefault,
+    /// For important notifications or alerts, may be shown larger or higher in the list.
+    PriorityHigh,
+    /// For the most important items, needing the user's prompt attention or input.
+    PriorityMax,
+}
+
+/// Whether a notification is shown on a secure lock screen.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Visibility {
+    /// No visibility given, Android uses `Private`.
+    #[serde(rename = "VISIBILITY_UNSPECIFIED")]
     Unspecified,
+    /// Shown on the lock screen, with its sensitive content hidden.
     Private,
+    /// Shown in full on the lock screen.
     Public,
-    Secret
+    /// Not shown on the lock screen at all.
+    Secret,
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-#[serde(rename_all = "lowercase")]
+/// When a notification may be proxied by the device.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Proxy {
+    /// No proxy setting given, FCM uses `IfPriorityLowered`.
+    #[serde(rename = "PROXY_UNSPECIFIED")]
     Unspecified,
+    /// Proxied whenever the device allows it.
     Allow,
+    /// Never proxied.
     Deny,
-    IfPriorityLowered
+    /// Proxied only when the priority of the message was lowered from high
+    /// to normal on the device.
+    IfPriorityLowered,
 }
 
-#[derive(Serialize, Debug, PartialEq)]
+/// A basic notification shown on every platform. Use the corresponding
+/// `SharedNotificationBuilder` to get an instance. The Android and APNs
+/// specific settings of a message override its fields.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
 pub struct Notification<'a> {
 
     /// The notification's title.
     #[serde(skip_serializing_if = "Option::is_none")]
-    title: Option<&'a str>,
+    title: Option<Cow<'a, str>>,
 
     /// The notification's body text.
     #[serde(skip_serializing_if = "Option::is_none")]
-    body: Option<&'a str>,
+    body: Option<Cow<'a, str>>,
 
     /// Contains the URL of an image that is going to be downloaded on the device and
     /// displayed in a notification. JPEG, PNG, BMP have full support across platforms.
@@ -51,87 +115,193 @@ pub struct Notification<'a> {
     /// Quota usage and implications/costs for hosting image on Firebase Storage:
     /// https://firebase.google.com/pricing
     #[serde(skip_serializing_if = "Option::is_none")]
-    image: Option<&'a str>
+    image: Option<Cow<'a, str>>
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-pub struct LightSettings<'a> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    light_on_duration: Option<&'a str>,
+impl<'a> Notification<'a> {
+    /// Get a `SharedNotificationBuilder` to build a new notification.
+    pub fn builder() -> SharedNotificationBuilder<'a> {
+        SharedNotificationBuilder::new()
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    light_off_duration: Option<&'a str>,
+    /// Fill the fields not set in this notification from another one.
+    fn or(self, other: Notification<'a>) -> Notification<'a> {
+        Notification {
+            title: self.title.or(other.title),
+            body: self.body.or(other.body),
+            image: self.image.or(other.image),
+        }
+    }
+}
+
+/// The title and body of a legacy notification are shown on every platform.
+impl<'a> From<&NotificationV1<'a>> for Notification<'a> {
+    fn from(notification: &NotificationV1<'a>) -> Notification<'a> {
+        Notification {
+            title: notification.title.clone(),
+            body: notification.body.clone(),
+            image: None,
+        }
+    }
+}
+
+/// A builder to get a `Notification` instance, shown on every platform.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{Message, SharedNotificationBuilder};
+///
+/// let mut builder = SharedNotificationBuilder::new();
+/// builder.title("Australia vs New Zealand");
+/// builder.body("3 runs to win in 1 ball");
+/// builder.image("https://example.com/scoreboard.png");
+///
+/// let mut message = Message::to_token("<registration id>");
+/// message.shared_notification(builder.finalize());
+/// ```
+#[derive(Default, Debug)]
+pub struct SharedNotificationBuilder<'a> {
+    title: Option<Cow<'a, str>>,
+    body: Option<Cow<'a, str>>,
+    image: Option<Cow<'a, str>>,
+}
+
+impl<'a> SharedNotificationBuilder<'a> {
+    /// Get a new `SharedNotificationBuilder` instance.
+    pub fn new() -> SharedNotificationBuilder<'a> {
+        Self::default()
+    }
+
+    /// Set the title of the notification.
+    pub fn title<S: Into<Cow<'a, str>>>(&mut self, title: S) -> &mut Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the body of the notification.
+    pub fn body<S: Into<Cow<'a, str>>>(&mut self, body: S) -> &mut Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set the URL of an image shown in the notification.
+    pub fn image<S: Into<Cow<'a, str>>>(&mut self, image: S) -> &mut Self {
+        self.image = Some(image.into());
+        self
+    }
+
+    /// Complete the build and get a `Notification` instance.
+    pub fn finalize(self) -> Notification<'a> {
+        Notification {
+            title: self.title,
+            body: self.body,
+            image: self.image,
+        }
+    }
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-pub struct AndroidNotification<'a> { // new
+/// How the LED of the device blinks for a notification, if it has one.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct LightSettings {
+    /// The color of the LED.
+    color: Color,
+
+    /// How long the LED is on while blinking.
+    #[serde(serialize_with = "duration::serialize")]
+    light_on_duration: Duration,
+
+    /// How long the LED is off while blinking.
+    #[serde(serialize_with = "duration::serialize")]
+    light_off_duration: Duration,
+}
+
+impl LightSettings {
+    /// Blink the LED in the given color, on and off for the given durations.
+    pub fn new(color: Color, light_on_duration: Duration, light_off_duration: Duration) -> LightSettings {
+        LightSettings {
+            color,
+            light_on_duration,
+            light_off_duration,
+        }
+    }
+}
+
+/// A notification shown on Android devices. Use the corresponding
+/// `AndroidNotificationBuilder` to get an instance. Its fields override
+/// those of the shared `Notification`.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct AndroidNotification<'a> {
     /// The notification's title.
     #[serde(skip_serializing_if = "Option::is_none")]
-    title: Option<&'a str>,
+    title: Option<Cow<'a, str>>,
 
     /// The notification's body text. If present, it will override
     /// google.firebase.fcm.v1.Notification.body.
     #[serde(skip_serializing_if = "Option::is_none")]
-    body: Option<&'a str>,
+    body: Option<Cow<'a, str>>,
 
     /// The notification's icon. Sets the notification icon to myicon for drawable
     /// resource myicon. If you don't send this key in the request, FCM displays the
     /// launcher icon specified in your app manifest.
     #[serde(skip_serializing_if = "Option::is_none")]
-    icon: Option<&'a str>,
+    icon: Option<Cow<'a, str>>,
 
     /// The notification's icon color, expressed in #rrggbb format.
     #[serde(skip_serializing_if = "Option::is_none")]
-    color: Option<&'a str>,
+    color: Option<Cow<'a, str>>,
 
     /// The sound to play when the device receives the notification. Supports "default" or the
     /// filename of a sound resource bundled in the app. Sound files must reside in /res/raw/.
     #[serde(skip_serializing_if = "Option::is_none")]
-    sound: Option<&'a str>,
+    sound: Option<Cow<'a, str>>,
 
     /// Identifier used to replace existing notifications in the notification drawer. If not
     /// specified, each request creates a new notification. If specified and a notification
     /// with the same tag is already being shown, the new notification replaces the existing
     /// one in the notification drawer.
     #[serde(skip_serializing_if = "Option::is_none")]
-    tag: Option<&'a str>,
+    tag: Option<Cow<'a, str>>,
 
     /// The action associated with a user click on the notification. If specified, an activity
     /// with a matching intent filter is launched when a user clicks on the notification.
     #[serde(skip_serializing_if = "Option::is_none")]
-    click_action: Option<&'a str>,
+    click_action: Option<Cow<'a, str>>,
 
     /// The key to the body string in the app's string resources to use to localize the body text
     /// to the user's current localization. See String Resources for more information.
     #[serde(skip_serializing_if = "Option::is_none")]
-    body_loc_key: Option<&'a str>,
+    body_loc_key: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    body_loc_args: Option<Vec<&'a str>>,
+    body_loc_args: Option<Vec<Cow<'a, str>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    title_loc_key: Option<&'a str>,
+    title_loc_key: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    title_loc_args: Option<Vec<&'a str>>,
+    title_loc_args: Option<Vec<Cow<'a, str>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    channel_id: Option<&'a str>,
+    channel_id: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    ticker: Option<&'a str>,
+    ticker: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     sticky: Option<bool>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    event_time: Option<&'a str>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::serialize_option"
+    )]
+    event_time: Option<DateTime<Utc>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     local_only: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    notification_priority: Option<Priority>,
+    notification_priority: Option<NotificationPriority>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     default_sound: Option<bool>,
@@ -142,8 +312,16 @@ pub struct AndroidNotification<'a> { // new
     #[serde(skip_serializing_if = "Option::is_none")]
     default_light_settings: Option<bool>,
 
+    /// How the LED blinks, if the device has one. Overrides
+    /// `default_light_settings`.
     #[serde(skip_serializing_if = "Option::is_none")]
-    vibrate_timings: Option<Vec<&'a str>>,
+    light_settings: Option<LightSettings>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "duration::serialize_option_seq"
+    )]
+    vibrate_timings: Option<Vec<Duration>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     visibility: Option<Visibility>,
@@ -154,10 +332,10 @@ pub struct AndroidNotification<'a> { // new
     /// Contains the URL of an image that is going to be displayed in a notification.
     /// If present, it will override google.firebase.fcm.v1.Notification.image.
     #[serde(skip_serializing_if = "Option::is_none")]
-    image: Option<&'a str>,
+    image: Option<Cow<'a, str>>,
 
-    /// Contains the URL of an image that is going to be displayed in a notification.
-    /// If present, it will override google.firebase.fcm.v1.Notification.image.
+    /// If set to true, the notification is shown directly by the app instead of being
+    /// proxied by the device's notification proxy.
     #[serde(skip_serializing_if = "Option::is_none")]
     bypass_proxy_notification: Option<bool>,
 
@@ -166,19 +344,306 @@ pub struct AndroidNotification<'a> { // new
     proxy: Option<Proxy>
 }
 
-#[derive(Serialize, Debug, PartialEq)]
+impl<'a> AndroidNotification<'a> {
+    /// Get an `AndroidNotificationBuilder` to build a new notification.
+    pub fn builder() -> AndroidNotificationBuilder<'a> {
+        AndroidNotificationBuilder::new()
+    }
+
+    /// Fill the fields not set in this notification from the Android
+    /// settings of a legacy notification.
+    fn or_legacy(self, legacy: &NotificationV1<'a>) -> AndroidNotification<'a> {
+        AndroidNotification {
+            icon: self.icon.or_else(|| legacy.icon.clone()),
+            color: self.color.or_else(|| legacy.color.clone()),
+            sound: self.sound.or_else(|| legacy.sound.clone()),
+            tag: self.tag.or_else(|| legacy.tag.clone()),
+            click_action: self.click_action.or_else(|| legacy.click_action.clone()),
+            body_loc_key: self.body_loc_key.or_else(|| legacy.body_loc_key.clone()),
+            body_loc_args: self.body_loc_args.or_else(|| legacy.body_loc_args.clone()),
+            title_loc_key: self.title_loc_key.or_else(|| legacy.title_loc_key.clone()),
+            title_loc_args: self.title_loc_args.or_else(|| legacy.title_loc_args.clone()),
+            ..self
+        }
+    }
+}
+
+/// A builder to get an `AndroidNotification` instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{AndroidNotificationBuilder, Message, Visibility};
+///
+/// let mut builder = AndroidNotificationBuilder::new();
+/// builder
+///     .channel_id("scores")
+///     .sticky(true)
+///     .visibility(Visibility::Public);
+///
+/// let mut message = Message::to_token("<registration id>");
+/// message.android_notification(builder.finalize());
+/// ```
+#[derive(Default, Debug)]
+pub struct AndroidNotificationBuilder<'a> {
+    notification: AndroidNotification<'a>,
+}
+
+impl<'a> AndroidNotificationBuilder<'a> {
+    /// Get a new `AndroidNotificationBuilder` instance.
+    pub fn new() -> AndroidNotificationBuilder<'a> {
+        Self::default()
+    }
+
+    /// Set the title of the notification.
+    pub fn title<S: Into<Cow<'a, str>>>(&mut self, title: S) -> &mut Self {
+        self.notification.title = Some(title.into());
+        self
+    }
+
+    /// Set the body of the notification.
+    pub fn body<S: Into<Cow<'a, str>>>(&mut self, body: S) -> &mut Self {
+        self.notification.body = Some(body.into());
+        self
+    }
+
+    /// Set the drawable resource used as the notification icon.
+    pub fn icon<S: Into<Cow<'a, str>>>(&mut self, icon: S) -> &mut Self {
+        self.notification.icon = Some(icon.into());
+        self
+    }
+
+    /// The color of the icon, in #rrggbb format.
+    pub fn color<S: Into<Cow<'a, str>>>(&mut self, color: S) -> &mut Self {
+        self.notification.color = Some(color.into());
+        self
+    }
+
+    /// Set the sound to be played, `default` or a sound resource of the app.
+    pub fn sound<S: Into<Cow<'a, str>>>(&mut self, sound: S) -> &mut Self {
+        self.notification.sound = Some(sound.into());
+        self
+    }
+
+    /// Tagging a notification allows you to replace existing notifications
+    /// with the same tag with this new notification.
+    pub fn tag<S: Into<Cow<'a, str>>>(&mut self, tag: S) -> &mut Self {
+        self.notification.tag = Some(tag.into());
+        self
+    }
+
+    /// The activity with a matching intent filter launched when the user
+    /// clicks on the notification.
+    pub fn click_action<S: Into<Cow<'a, str>>>(&mut self, click_action: S) -> &mut Self {
+        self.notification.click_action = Some(click_action.into());
+        self
+    }
+
+    /// Set the body key string for localization.
+    pub fn body_loc_key<S: Into<Cow<'a, str>>>(&mut self, body_loc_key: S) -> &mut Self {
+        self.notification.body_loc_key = Some(body_loc_key.into());
+        self
+    }
+
+    /// String value to replace format specifiers in the body string.
+    pub fn body_loc_args<S>(&mut self, body_loc_args: &[S]) -> &mut Self
+    where
+        S: Into<Cow<'a, str>> + Clone,
+    {
+        let converted = body_loc_args.iter().cloned().map(Into::into).collect();
+
+        self.notification.body_loc_args = Some(converted);
+        self
+    }
+
+    /// Set the title key string for localization.
+    pub fn title_loc_key<S: Into<Cow<'a, str>>>(&mut self, title_loc_key: S) -> &mut Self {
+        self.notification.title_loc_key = Some(title_loc_key.into());
+        self
+    }
+
+    /// String value to replace format specifiers in the title string.
+    pub fn title_loc_args<S>(&mut self, title_loc_args: &[S]) -> &mut Self
+    where
+        S: Into<Cow<'a, str>> + Clone,
+    {
+        let converted = title_loc_args.iter().cloned().map(Into::into).collect();
+
+        self.notification.title_loc_args = Some(converted);
+        self
+    }
+
+    /// The notification channel the notification is posted to. The app
+    /// must create the channel before a notification can use it.
+    pub fn channel_id<S: Into<Cow<'a, str>>>(&mut self, channel_id: S) -> &mut Self {
+        self.notification.channel_id = Some(channel_id.into());
+        self
+    }
+
+    /// The text read out by accessibility services.
+    pub fn ticker<S: Into<Cow<'a, str>>>(&mut self, ticker: S) -> &mut Self {
+        self.notification.ticker = Some(ticker.into());
+        self
+    }
+
+    /// When `true`, the notification stays when the user clicks on it.
+    pub fn sticky(&mut self, sticky: bool) -> &mut Self {
+        self.notification.sticky = Some(sticky);
+        self
+    }
+
+    /// When the event the notification is about happened.
+    pub fn event_time(&mut self, event_time: DateTime<Utc>) -> &mut Self {
+        self.notification.event_time = Some(event_time);
+        self
+    }
+
+    /// When `true`, the notification is not bridged to wearables.
+    pub fn local_only(&mut self, local_only: bool) -> &mut Self {
+        self.notification.local_only = Some(local_only);
+        self
+    }
+
+    /// The relative priority of the notification.
+    pub fn notification_priority(&mut self, notification_priority: NotificationPriority) -> &mut Self {
+        self.notification.notification_priority = Some(notification_priority);
+        self
+    }
+
+    /// When `true`, the default sound of the device is played.
+    pub fn default_sound(&mut self, default_sound: bool) -> &mut Self {
+        self.notification.default_sound = Some(default_sound);
+        self
+    }
+
+    /// When `true`, the default vibration pattern of the device is used.
+    pub fn default_vibrate_timings(&mut self, default_vibrate_timings: bool) -> &mut Self {
+        self.notification.default_vibrate_timings = Some(default_vibrate_timings);
+        self
+    }
+
+    /// When `true`, the default LED light settings of the device are used.
+    pub fn default_light_settings(&mut self, default_light_settings: bool) -> &mut Self {
+        self.notification.default_light_settings = Some(default_light_settings);
+        self
+    }
+
+    /// How the LED of the device blinks for the notification.
+    ///
+    /// # Examples:
+    /// ```rust
+    /// use fcm::{AndroidNotificationBuilder, Color, LightSettings};
+    /// use std::time::Duration;
+    ///
+    /// let mut builder = AndroidNotificationBuilder::new();
+    /// builder.light_settings(LightSettings::new(
+    ///     Color::from_hex("#00ff00")?,
+    ///     Duration::from_millis(500),
+    ///     Duration::from_secs(2),
+    /// ));
+    /// # Ok::<(), fcm::Error>(())
+    /// ```
+    pub fn light_settings(&mut self, light_settings: LightSettings) -> &mut Self {
+        self.notification.light_settings = Some(light_settings);
+        self
+    }
+
+    /// The vibration pattern, alternating off and on durations.
+    pub fn vibrate_timings(&mut self, vibrate_timings: Vec<Duration>) -> &mut Self {
+        self.notification.vibrate_timings = Some(vibrate_timings);
+        self
+    }
+
+    /// Whether the notification is shown on a secure lock screen.
+    pub fn visibility(&mut self, visibility: Visibility) -> &mut Self {
+        self.notification.visibility = Some(visibility);
+        self
+    }
+
+    /// The number of items the notification stands for, shown on the app
+    /// icon badge by supporting launchers.
+    pub fn notification_count(&mut self, notification_count: i64) -> &mut Self {
+        self.notification.notification_count = Some(notification_count);
+        self
+    }
+
+    /// Set the URL of an image shown in the notification.
+    pub fn image<S: Into<Cow<'a, str>>>(&mut self, image: S) -> &mut Self {
+        self.notification.image = Some(image.into());
+        self
+    }
+
+    /// When `true`, the notification is shown by the app instead of the
+    /// notification proxy of the device.
+    pub fn bypass_proxy_notification(&mut self, bypass_proxy_notification: bool) -> &mut Self {
+        self.notification.bypass_proxy_notification = Some(bypass_proxy_notification);
+        self
+    }
+
+    /// When the notification may be proxied.
+    pub fn proxy(&mut self, proxy: Proxy) -> &mut Self {
+        self.notification.proxy = Some(proxy);
+        self
+    }
+
+    /// Complete the build and get an `AndroidNotification` instance.
+    pub fn finalize(self) -> AndroidNotification<'a> {
+        self.notification
+    }
+}
+
+/// Options for features provided by the FCM SDK for Android.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct AndroidFcmOptions<'a> {
+    /// The label associated with the message's analytics data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analytics_label: Option<Cow<'a, str>>,
+}
+
+/// Options for features provided by the FCM SDK on all platforms.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct FcmOptions<'a> {
+    /// The label associated with the message's analytics data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analytics_label: Option<Cow<'a, str>>,
+}
+
+/// The longest analytics label FCM accepts.
+pub const MAX_ANALYTICS_LABEL_LENGTH: usize = 50;
+
+/// Check a label against the pattern FCM documents for analytics labels,
+/// `^[a-zA-Z0-9-_.~%]{1,50}$`. The error describes what is wrong.
+fn validate_analytics_label(analytics_label: &str) -> Result<(), String> {
+    if analytics_label.is_empty() || analytics_label.len() > MAX_ANALYTICS_LABEL_LENGTH {
+        return Err(format!(
+            "analytics label must be 1 to {} characters long, got {:?}",
+            MAX_ANALYTICS_LABEL_LENGTH, analytics_label
+        ));
+    }
+
+    if let Some(c) = analytics_label.chars().find(|c| !validate::is_name_char(*c)) {
+        return Err(format!(
+            "analytics label {:?} contains {:?}, only letters, digits and -_.~% are allowed",
+            analytics_label, c
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
 pub struct AndroidConfig<'a> {
     /// An identifier of a group of messages that can be collapsed, so that only the last
     /// message gets sent when delivery can be resumed. A maximum of 4 different collapse
     /// keys is allowed at any given time.
     #[serde(skip_serializing_if = "Option::is_none")]
-    collapse_key: Option<&'a str>,
+    collapse_key: Option<Cow<'a, str>>,
 
     /// Message priority. Can take "normal" and "high" values.
     #[serde(skip_serializing_if = "Option::is_none")]
     priority: Option<Priority>,
 
-    /// How long (in seconds) the message should be kept in FCM storage if the device is offline.
+    /// How long the message should be kept in FCM storage if the device is offline.
     /// The maximum time to live supported is 4 weeks, and the default value is 4 weeks if not set.
     /// Set it to 0 if want to send the message immediately. In JSON format, the Duration type is
     /// encoded as a string rather than an object, where the string ends in the suffix "s"
@@ -186,189 +651,294 @@ pub struct AndroidConfig<'a> {
     /// as fractional seconds. For example, 3 seconds with 0 nanoseconds should be encoded in JSON
     /// format as "3s", while 3 seconds and 1 nanosecond should be expressed in JSON format as
     /// "3.000000001s". The ttl will be rounded down to the nearest second.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    ttl: Option<&'a str>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "duration::serialize_option"
+    )]
+    ttl: Option<Duration>,
 
     /// Package name of the application where the registration token must match in order to
     /// receive the message.
     #[serde(skip_serializing_if = "Option::is_none")]
-    restricted_package_name: Option<&'a str>,
+    restricted_package_name: Option<Cow<'a, str>>,
 
     /// An object containing a list of "key": value pairs
     #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<Value>,
-
+    data: Option<DataPayload>,
 
-    /// If set to true, messages will be allowed to be delivered to the app while the device
-    /// is in direct boot mode.
+    /// Notification to send to android devices.
     #[serde(skip_serializing_if = "Option::is_none")]
-    direct_boot_ok: Option<bool>,
-}
+    notification: Option<AndroidNotification<'a>>,
 
-#[derive(Serialize, Debug, PartialEq)]
-pub struct ApnsFcmOptions<'a> {
+    /// Options for features provided by the FCM SDK for Android.
     #[serde(skip_serializing_if = "Option::is_none")]
-    analytics_label: Option<&'a str>,
+    fcm_options: Option<AndroidFcmOptions<'a>>,
 
+    /// If set to true, messages will be allowed to be delivered to the app while the device
+    /// is in direct boot mode.
     #[serde(skip_serializing_if = "Option::is_none")]
-    image: Option<&'a str>,
+    direct_boot_ok: Option<bool>,
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-pub struct ApnsConfig<'a> {
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct MessageBody<'a> {
+    /// Arbitrary key/value payload, delivered to the app on every platform.
     #[serde(skip_serializing_if = "Option::is_none")]
-    headers: Option<Value>,
+    data: Option<DataPayload>,
 
+    /// Basic notification template to use across all platforms.
     #[serde(skip_serializing_if = "Option::is_none")]
-    payload: Option<Value>,
+    notification: Option<Notification<'a>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    fcm_options: Option<ApnsFcmOptions<'a>>,
-}
+    android: Option<AndroidConfig<'a>>,
 
-#[derive(Serialize, Debug, PartialEq)]
-pub struct MessageBody<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    name: Option<&'a str>,
+    apns: Option<ApnsConfig<'a>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    android: Option<AndroidConfig<'a>>,
+    webpush: Option<WebpushConfig<'a>>,
 
+    /// Options for features provided by the FCM SDK on all platforms.
     #[serde(skip_serializing_if = "Option::is_none")]
-    apns: Option<ApnsConfig<'a>>,
+    fcm_options: Option<FcmOptions<'a>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    topic: Option<&'a str>,
+    topic: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    condition: Option<&'a str>,
+    condition: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    token: Option<&'a str>
+    token: Option<Cow<'a, str>>
 }
 
-#[derive(Serialize, Debug, PartialEq)]
+/// A message ready to be sent with `Client::send`. Get one from a
+/// `MessageBuilder`.
+///
+/// Every string of a message is either borrowed or owned, so a message
+/// built from owned strings is a `Message<'static>` that can be moved to
+/// another task, queued or kept as a template.
+///
+/// Cloning a `Message<'static>` copies each of its strings. To send the
+/// same message from several tasks, share an `Arc<Message>` instead, which
+/// `Client::send` takes as is.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::Message;
+/// use std::sync::Arc;
+///
+/// fn build(token: String) -> Result<Message<'static>, fcm::Error> {
+///     Message::to_token(token).finalize()
+/// }
+///
+/// let message = Arc::new(build("<registration id>".to_string())?);
+/// # Ok::<(), fcm::Error>(())
+/// ```
+#[derive(Serialize, Debug, PartialEq, Clone)]
 pub struct Message<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     validate_only: Option<bool>,
     message: MessageBody<'a>,
 }
 
+impl<'a> Message<'a> {
+    /// Get a `MessageBuilder` for a message to the device with the given
+    /// registration token.
+    pub fn to_token<S: Into<Cow<'a, str>>>(token: S) -> MessageBuilder<'a, FixedTarget> {
+        MessageBuilder::with_target(Some(token.into()), None, None)
+    }
+
+    /// Get a `MessageBuilder` for a message to the subscribers of a topic,
+    /// named without the `/topics/` prefix.
+    pub fn to_topic<S: Into<Cow<'a, str>>>(topic: S) -> MessageBuilder<'a, FixedTarget> {
+        MessageBuilder::with_target(None, Some(topic.into()), None)
+    }
+
+    /// Get a `MessageBuilder` for a message to the devices subscribed to a
+    /// combination of topics. Either a string such as
+    /// `'TopicA' in topics && 'TopicB' in topics`, or a `Condition`.
+    pub fn to_condition<C: Into<Cow<'a, str>>>(condition: C) -> MessageBuilder<'a, FixedTarget> {
+        MessageBuilder::with_target(None, None, Some(condition.into()))
+    }
+
+    /// The registration token the message is sent to, if any.
+    pub(crate) fn token(&self) -> Option<&str> {
+        self.message.token.as_deref()
+    }
+}
+
+/// Marks a `MessageBuilder` from the deprecated `new` and `new_multi`,
+/// whose target can still be replaced with `token`, `topic` or `condition`.
+#[derive(Debug, Clone)]
+pub enum LegacyTarget {}
+
+/// Marks a `MessageBuilder` from `Message::to_token`, `Message::to_topic` or
+/// `Message::to_condition`, whose target can't be changed anymore.
+#[derive(Debug, Clone)]
+pub enum FixedTarget {}
+
 ///
-/// A builder to get a `Message` instance.
+/// A builder to get a `Message` instance. Get one for the target of the
+/// message from `Message::to_token`, `Message::to_topic` or
+/// `Message::to_condition`.
 ///
 /// # Examples
 ///
 /// ```rust
-/// use fcm::MessageBuilder;
+/// use fcm::Message;
 ///
-/// let mut builder = MessageBuilder::new("<FCM API Key>", "<registration id>");
+/// let mut builder = Message::to_token("<registration id>");
 /// builder.dry_run(true);
-/// let message = builder.finalize();
+/// let message = builder.finalize()?;
+/// # Ok::<(), fcm::Error>(())
+/// ```
+///
+/// A message has exactly one target, so a second one doesn't compile:
+///
+/// ```compile_fail
+/// use fcm::Message;
+///
+/// let mut builder = Message::to_token("<registration id>");
+/// builder.topic("news");
 /// ```
-#[derive(Debug)]
-pub struct MessageBuilder<'a> {
-    api_key: &'a str,
+#[derive(Debug, Clone)]
+pub struct MessageBuilder<'a, T = LegacyTarget> {
+    target: PhantomData<T>,
     /// target
-    token: Option<&'a str>,
-    topic: Option<&'a str>,
-    condition: Option< &'a str>,
+    token: Option<Cow<'a, str>>,
+    topic: Option<Cow<'a, str>>,
+    condition: Option<Cow<'a, str>>,
     /// end target
     validate_only: Option<bool>,
-    name: Option<&'a str>,
-    collapse_key: Option<&'a str>,
+    collapse_key: Option<Cow<'a, str>>,
     content_available: Option<bool>,
-    data: Option<Value>,
+    data: Option<DataPayload>,
     delay_while_idle: Option<bool>,
-    dry_run: Option<bool>,
     notification: Option<NotificationV1<'a>>,
+    shared_notification: Option<Notification<'a>>,
+    android_notification: Option<AndroidNotification<'a>>,
+    android_analytics_label: Option<Cow<'a, str>>,
+    analytics_label: Option<Cow<'a, str>>,
+    apns: Option<ApnsConfig<'a>>,
+    webpush: Option<WebpushConfig<'a>>,
     priority: Option<Priority>,
     registration_ids: Option<Vec<Cow<'a, str>>>,
-    restricted_package_name: Option<&'a str>,
-    time_to_live: Option<&'a str>,
-    to: Option<&'a str>,
+    restricted_package_name: Option<Cow<'a, str>>,
+    direct_boot_ok: Option<bool>,
+    time_to_live: Option<Duration>,
+    to: Option<Cow<'a, str>>,
     mutable_content: Option<bool>,
 }
 
-impl<'a> MessageBuilder<'a> {
+impl<'a> MessageBuilder<'a, LegacyTarget> {
     /// Get a new instance of Message. You need to supply to.
-    pub fn new(api_key: &'a str, to: &'a str) -> Self {
-        MessageBuilder {
-            api_key,
-            token: None,
-            topic: None,
-            condition: None,
-            validate_only: None,
-            to: Some(to),
-            registration_ids: None,
-            collapse_key: None,
-            priority: None,
-            content_available: None,
-            delay_while_idle: None,
-            time_to_live: None,
-            restricted_package_name: None,
-            dry_run: None,
-            data: None,
-            notification: None,
-            mutable_content: None,
-            name: None,
-        }
+    #[deprecated(
+        since = "0.10.0",
+        note = "use `Message::to_token`, `Message::to_topic` or `Message::to_condition`"
+    )]
+    pub fn new(_api_key: &'a str, to: &'a str) -> Self {
+        let mut builder = Self::with_target(None, None, None);
+        builder.to = Some(to.into());
+        builder
     }
 
     /// Get a new instance of Message. You need to supply registration ids.
-    pub fn new_multi<S>(api_key: &'a str, ids: &'a [S]) -> Self
+    #[deprecated(
+        since = "0.10.0",
+        note = "the v1 API sends a message per token, use `Message::to_token`"
+    )]
+    pub fn new_multi<S>(_api_key: &'a str, ids: &[S]) -> Self
     where
-        S: Into<Cow<'a, str>> + AsRef<str>,
+        S: Into<Cow<'a, str>> + Clone,
     {
-        let converted = ids.iter().map(|a| a.as_ref().into()).collect();
+        let mut builder = Self::with_target(None, None, None);
+        builder.registration_ids(ids);
+        builder
+    }
 
+    /// Send the message to several registration tokens at once. The v1 API
+    /// has no equivalent, so `finalize` fails when ids are set.
+    pub fn registration_ids<S>(&mut self, ids: &[S]) -> &mut Self
+    where
+        S: Into<Cow<'a, str>> + Clone,
+    {
+        let converted = ids.iter().cloned().map(Into::into).collect();
+
+        self.registration_ids = Some(converted);
+        self
+    }
+
+    /// Send the message to the subscribers of a topic. `finalize` fails if
+    /// `new` was given a registration token, give it an empty one instead.
+    pub fn topic<S: Into<Cow<'a, str>>>(&mut self, topic: S) -> &mut Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    /// Send the message to this registration token instead of the one given
+    /// to `new`.
+    pub fn token<S: Into<Cow<'a, str>>>(&mut self, token: S) -> &mut Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Send the message to the devices subscribed to a combination of
+    /// topics, either a string such as `'TopicA' in topics && 'TopicB' in topics`,
+    /// or a `Condition`. `finalize` fails if `new` was given a registration
+    /// token, give it an empty one instead.
+    pub fn condition<C: Into<Cow<'a, str>>>(&mut self, condition: C) -> &mut Self {
+        self.condition = Some(condition.into());
+        self
+    }
+}
+
+impl<'a, T> MessageBuilder<'a, T> {
+    fn with_target(token: Option<Cow<'a, str>>, topic: Option<Cow<'a, str>>, condition: Option<Cow<'a, str>>) -> Self {
         MessageBuilder {
-            api_key,
-            token: None,
-            topic: None,
-            condition: None,
+            target: PhantomData,
+            token,
+            topic,
+            condition,
             validate_only: None,
             to: None,
-            registration_ids: Some(converted),
+            registration_ids: None,
             collapse_key: None,
             priority: None,
             content_available: None,
             delay_while_idle: None,
             time_to_live: None,
             restricted_package_name: None,
-            dry_run: None,
+            direct_boot_ok: None,
             data: None,
             notification: None,
+            shared_notification: None,
+            android_notification: None,
+            android_analytics_label: None,
+            analytics_label: None,
+            apns: None,
+            webpush: None,
             mutable_content: None,
-            name: None,
         }
     }
 
-    /// String value to replace format specifiers in the body string.
-    pub fn registration_ids<S>(&mut self, ids: &'a [S]) -> &mut Self
-    where
-        S: Into<Cow<'a, str>> + AsRef<str>,
-    {
-        let converted = ids.iter().map(|a| a.as_ref().into()).collect();
-
-        self.registration_ids = Some(converted);
-        self
-    }
-
     /// Set this parameter to identify groups of messages that can be collapsed.
-    pub fn collapse_key(&mut self, collapse_key: &'a str) -> &mut Self {
-        self.collapse_key = Some(collapse_key);
+    pub fn collapse_key<S: Into<Cow<'a, str>>>(&mut self, collapse_key: S) -> &mut Self {
+        self.collapse_key = Some(collapse_key.into());
         self
     }
 
     /// Set the priority of the message. You can set Normal or High priorities.
     /// # Examples:
     /// ```rust
-    /// use fcm::{MessageBuilder, Priority};
+    /// use fcm::{Message, Priority};
     ///
-    /// let mut builder = MessageBuilder::new("<FCM API Key>", "<registration id>");
+    /// let mut builder = Message::to_token("<registration id>");
     /// builder.priority(Priority::High);
-    /// let message = builder.finalize();
+    /// let message = builder.finalize()?;
+    /// # Ok::<(), fcm::Error>(())
     /// ```
     pub fn priority(&mut self, priority: Priority) -> &mut Self {
         self.priority = Some(priority);
@@ -382,109 +952,256 @@ impl<'a> MessageBuilder<'a> {
     }
 
     /// When set to `true`, sends the message only when the device is active.
+    /// The v1 API has no equivalent, so `finalize` fails when it is `true`.
     pub fn delay_while_idle(&mut self, delay_while_idle: bool) -> &mut Self {
         self.delay_while_idle = Some(delay_while_idle);
         self
     }
 
-    /// How long (in seconds) to keep the message on FCM servers in case the device
-    /// is offline. The maximum and default is 4 weeks.
-    pub fn time_to_live(&mut self, time_to_live: &'a str) -> &mut Self {
+    /// How long to keep the message on FCM servers in case the device is
+    /// offline. The maximum and default is 4 weeks, `finalize` fails for a
+    /// longer one.
+    pub fn time_to_live(&mut self, time_to_live: Duration) -> &mut Self {
         self.time_to_live = Some(time_to_live);
         self
     }
 
     /// Package name of the application where the registration tokens must match.
-    pub fn restricted_package_name(&mut self, restricted_package_name: &'a str) -> &mut Self {
-        self.restricted_package_name = Some(restricted_package_name);
+    pub fn restricted_package_name<S: Into<Cow<'a, str>>>(&mut self, restricted_package_name: S) -> &mut Self {
+        self.restricted_package_name = Some(restricted_package_name.into());
+        self
+    }
+
+    /// Whether the message may be delivered to the Android app while the
+    /// device is in direct boot mode.
+    pub fn direct_boot_ok(&mut self, direct_boot_ok: bool) -> &mut Self {
+        self.direct_boot_ok = Some(direct_boot_ok);
+        self
+    }
+
+    /// When set to `true`, FCM validates the message without actually sending it.
+    pub fn validate_only(&mut self, validate_only: bool) -> &mut Self {
+        self.validate_only = Some(validate_only);
         self
     }
 
     /// When set to `true`, allows you to test FCM without actually sending the message.
+    /// The same as `validate_only`.
     pub fn dry_run(&mut self, dry_run: bool) -> &mut Self {
-        self.dry_run = Some(dry_run);
-        self
+        self.validate_only(dry_run)
     }
 
     /// Use this to add custom key-value pairs to the message. This data
-    /// must be handled appropriately on the client end. The data can be
-    /// anything that Serde can serialize to JSON.
+    /// must be handled appropriately on the client end. FCM only accepts
+    /// strings as values, see `ToFcmData` for what can be given.
     ///
     /// # Examples:
     /// ```rust
-    /// use fcm::MessageBuilder;
+    /// use fcm::Message;
     /// use std::collections::HashMap;
     ///
     /// let mut map = HashMap::new();
     /// map.insert("message", "Howdy!");
     ///
-    /// let mut builder = MessageBuilder::new("<FCM API Key>", "<registration id>");
-    /// builder.data(&map);
-    /// let message = builder.finalize();
+    /// let mut builder = Message::to_token("<registration id>");
+    /// builder.data(&map)?;
+    /// let message = builder.finalize()?;
+    /// # Ok::<(), fcm::Error>(())
     /// ```
-    pub fn data(&mut self, data: &dyn erased_serde::Serialize) -> Result<&mut Self, serde_json::Error> {
-        self.data = Some(serde_json::to_value(data)?);
+    pub fn data(&mut self, data: &dyn ToFcmData) -> Result<&mut Self, FcmError> {
+        self.data = Some(data.to_fcm_data()?);
         Ok(self)
     }
 
-    /// Use this to set a `Notification` for the message.
+    /// Use this to set a legacy notification for the message. Its title and
+    /// body are shown on every platform, its other fields are sent as Android
+    /// and APNs settings.
     /// # Examples:
     /// ```rust
-    /// use fcm::{MessageBuilder, NotificationBuilder};
+    /// use fcm::{Message, NotificationBuilder};
     ///
     /// let mut builder = NotificationBuilder::new();
     /// builder.title("Hey!");
     /// builder.body("Do you want to catch up later?");
     /// let notification = builder.finalize();
     ///
-    /// let mut builder = MessageBuilder::new("<FCM API Key>", "<registration id>");
-    ///
-    /// let message = builder.finalize();
+    /// let mut builder = Message::to_token("<registration id>");
+    /// builder.notification(notification);
+    /// let message = builder.finalize()?;
+    /// # Ok::<(), fcm::Error>(())
     /// ```
     pub fn notification(&mut self, notification: NotificationV1<'a>) -> &mut Self {
         self.notification = Some(notification);
         self
     }
 
-    /// To set the `mutable_content` field on iOS
-    pub fn mutable_content(&mut self, mutable_content: bool) -> &mut Self {
-        self.mutable_content = Some(mutable_content);
+    /// Use this to set a `Notification` shown on every platform. Its fields
+    /// take precedence over the title and body of the legacy notification.
+    pub fn shared_notification(&mut self, notification: Notification<'a>) -> &mut Self {
+        self.shared_notification = Some(notification);
         self
     }
 
-    pub fn topic(&mut self, topic: &'a str) -> &mut Self {
-        self.topic = Some(topic);
+    /// Use this to set an `AndroidNotification` for Android devices. Its
+    /// fields take precedence over the shared and legacy notifications.
+    pub fn android_notification(&mut self, notification: AndroidNotification<'a>) -> &mut Self {
+        self.android_notification = Some(notification);
         self
     }
 
-    pub fn token(&mut self, token: &'a str) -> &mut Self {
-        self.token = Some(token);
+    /// The label associated with the analytics data of the message on
+    /// Android.
+    pub fn android_analytics_label<S: Into<Cow<'a, str>>>(&mut self, analytics_label: S) -> &mut Self {
+        self.android_analytics_label = Some(analytics_label.into());
         self
     }
 
-    pub fn condition(&mut self, condition: &'a str) -> &mut Self {
-        self.condition = Some(condition);
+    /// The label associated with the analytics data of the message on all
+    /// platforms, used to aggregate delivery data. Platform specific labels
+    /// take precedence.
+    pub fn analytics_label<S: Into<Cow<'a, str>>>(&mut self, analytics_label: S) -> &mut Self {
+        self.analytics_label = Some(analytics_label.into());
         self
     }
-    
-    pub fn finalize(self) -> Message<'a> {
-        Message {
+
+    /// The APNs specific settings of the message, built with an
+    /// `ApnsConfigBuilder`. Settings made here win over the ones derived
+    /// from the legacy notification, priority, `content_available` and
+    /// `mutable_content`.
+    pub fn apns(&mut self, apns: ApnsConfig<'a>) -> &mut Self {
+        self.apns = Some(apns);
+        self
+    }
+
+    /// The Web Push specific settings of the message, built with a
+    /// `WebpushConfigBuilder`.
+    pub fn webpush(&mut self, webpush: WebpushConfig<'a>) -> &mut Self {
+        self.webpush = Some(webpush);
+        self
+    }
+
+    /// To set the `mutable_content` field on iOS
+    pub fn mutable_content(&mut self, mutable_content: bool) -> &mut Self {
+        self.mutable_content = Some(mutable_content);
+        self
+    }
+
+    /// Complete the build and get a `Message` instance.
+    ///
+    /// Fails if more than one of `token`, `topic` and `condition` is set,
+    /// if there is no target at all, if an analytics label doesn't match
+    /// `^[a-zA-Z0-9-_.~%]{1,50}$`, or if a setting has no equivalent in the
+    /// v1 API.
+    pub fn finalize(self) -> Result<Message<'a>, FcmError> {
+        if self.registration_ids.is_some() {
+            return Err(FcmError::InvalidMessage(
+                "registration_ids is not supported by the v1 API, send a message per token".to_string(),
+            ));
+        }
+
+        if self.delay_while_idle == Some(true) {
+            return Err(FcmError::InvalidMessage(
+                "delay_while_idle is not supported by the v1 API".to_string(),
+            ));
+        }
+
+        if let Some(time_to_live) = self.time_to_live.filter(|ttl| *ttl > MAX_TIME_TO_LIVE) {
+            return Err(FcmError::InvalidMessage(format!(
+                "time_to_live of {} exceeds the maximum of {}",
+                duration::format(&time_to_live),
+                duration::format(&MAX_TIME_TO_LIVE)
+            )));
+        }
+
+        let analytics_labels = [
+            self.analytics_label.as_deref(),
+            self.android_analytics_label.as_deref(),
+            self.apns.as_ref().and_then(ApnsConfig::analytics_label),
+            self.webpush.as_ref().and_then(WebpushConfig::analytics_label),
+        ];
+
+        for analytics_label in analytics_labels.iter().flatten() {
+            validate_analytics_label(analytics_label).map_err(FcmError::InvalidMessage)?;
+        }
+
+        let to = self.to.filter(|to| !to.is_empty());
+
+        if to.is_some() && (self.topic.is_some() || self.condition.is_some()) {
+            return Err(FcmError::InvalidMessage(
+                "a topic or condition can't be combined with the registration token given to `new`".to_string(),
+            ));
+        }
+
+        let (token, topic, condition) = match (self.token, self.topic, self.condition) {
+            (None, None, None) => (to, None, None),
+            (token @ Some(_), None, None) => (token, None, None),
+            (None, topic @ Some(_), None) => (None, topic, None),
+            (None, None, condition @ Some(_)) => (None, None, condition),
+            _ => {
+                return Err(FcmError::InvalidMessage(
+                    "only one of token, topic and condition can be set".to_string(),
+                ))
+            }
+        };
+
+        if token.is_none() && topic.is_none() && condition.is_none() {
+            return Err(FcmError::InvalidMessage(
+                "a token, topic or condition is required".to_string(),
+            ));
+        }
+
+        let legacy_apns = ApnsConfig::from_legacy(
+            self.notification.as_ref(),
+            self.priority.as_ref(),
+            self.content_available,
+            self.mutable_content,
+        )?;
+
+        let apns = match self.apns {
+            Some(apns) => apns.or(legacy_apns),
+            None => legacy_apns,
+        };
+
+        let android_notification = match (self.android_notification, self.notification.as_ref()) {
+            (Some(android), Some(legacy)) => Some(android.or_legacy(legacy)),
+            (Some(android), None) => Some(android),
+            (None, legacy) => legacy.map(|legacy| AndroidNotification::default().or_legacy(legacy)),
+        };
+
+        let android = AndroidConfig {
+            collapse_key: self.collapse_key,
+            priority: self.priority,
+            ttl: self.time_to_live,
+            restricted_package_name: self.restricted_package_name,
+            direct_boot_ok: self.direct_boot_ok,
+            notification: android_notification.filter(|n| *n != AndroidNotification::default()),
+            fcm_options: self.android_analytics_label.map(|analytics_label| AndroidFcmOptions {
+                analytics_label: Some(analytics_label),
+            }),
+            ..AndroidConfig::default()
+        };
+
+        let notification = match (self.shared_notification, self.notification.as_ref()) {
+            (Some(shared), Some(legacy)) => Some(shared.or(legacy.into())),
+            (Some(shared), None) => Some(shared),
+            (None, legacy) => legacy.map(Notification::from),
+        };
+
+        Ok(Message {
             validate_only: self.validate_only,
             message: MessageBody {
-                name: self.name,
-                android: Some(AndroidConfig{
-                    priority: self.priority,
-                    collapse_key: self.collapse_key,
-                    data: self.data.clone(),
-                    ttl: self.time_to_live,
-                    restricted_package_name: self.restricted_package_name,
-                    direct_boot_ok: Some(false),
+                data: self.data,
+                notification: notification.filter(|n| *n != Notification::default()),
+                android: Some(android).filter(|android| *android != AndroidConfig::default()),
+                apns: Some(apns).filter(|apns| *apns != ApnsConfig::default()),
+                webpush: self.webpush,
+                fcm_options: self.analytics_label.map(|analytics_label| FcmOptions {
+                    analytics_label: Some(analytics_label),
                 }),
-                apns: None,
-                topic: self.topic,
-                token: self.to,
-                condition: self.condition,
+                topic,
+                token,
+                condition,
             },
-        }
+        })
     }
 }
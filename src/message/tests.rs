@@ -1,8 +1,14 @@
-use crate::notification::NotificationBuilder;
-use crate::{MessageBuilder, Priority};
+use crate::{
+    topic, AndroidNotification, AndroidNotificationBuilder, ApnsConfigBuilder, ApnsPayloadBuilder, ApnsPriority,
+    ApnsPushType, ApnsSound, Color, DataPayload, FixedTarget, InterruptionLevel, LightSettings, LiveActivityEvent,
+    Message, MessageBuilder, Notification, NotificationBuilder, NotificationPriority, Priority, Proxy,
+    SharedNotificationBuilder, ToFcmData, Visibility, WebpushConfigBuilder, WebpushNotificationAction,
+    WebpushNotificationBuilder, WebpushUrgency,
+};
+use chrono::{TimeZone, Utc};
 use serde::Serialize;
 use serde_json::json;
-use std::borrow::Cow;
+use std::time::Duration;
 
 #[derive(Serialize)]
 struct CustomData {
@@ -10,20 +16,28 @@ struct CustomData {
     bar: bool,
 }
 
+impl ToFcmData for CustomData {
+    fn to_fcm_data(&self) -> Result<DataPayload, crate::Error> {
+        DataPayload::from_serialize(self)
+    }
+}
+
 #[test]
 fn should_create_new_message() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.to, Some("token"));
+    assert_eq!(msg.message.token.as_deref(), Some("token"));
 }
 
 #[test]
 fn should_leave_nones_out_of_the_json() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
-    let payload = serde_json::to_string(&msg.body).unwrap();
+    let msg = Message::to_token("token").finalize().unwrap();
+    let payload = serde_json::to_string(&msg).unwrap();
 
     let expected_payload = json!({
-        "to": "token"
+        "message": {
+            "token": "token"
+        }
     })
     .to_string();
 
@@ -32,21 +46,18 @@ fn should_leave_nones_out_of_the_json() {
 
 #[test]
 fn should_add_custom_data_to_the_payload() {
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
 
     let data = CustomData { foo: "bar", bar: false };
 
     builder.data(&data).unwrap();
 
-    let msg = builder.finalize();
-    let payload = serde_json::to_string(&msg.body).unwrap();
+    let msg = builder.finalize().unwrap();
+    let payload = serde_json::to_string(&msg.message.data).unwrap();
 
     let expected_payload = json!({
-        "data": {
-            "foo": "bar",
-            "bar": false,
-        },
-        "to": "token"
+        "foo": "bar",
+        "bar": "false",
     })
     .to_string();
 
@@ -55,166 +66,1291 @@ fn should_add_custom_data_to_the_payload() {
 
 #[test]
 fn should_be_able_to_render_a_full_message_to_json() {
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut notification = NotificationBuilder::new();
+    notification
+        .title("Hey!")
+        .body("Do you want to catch up later?")
+        .icon("ic_chat")
+        .sound("chime.caf")
+        .badge("3")
+        .tag("chat")
+        .click_action("OPEN_CHAT");
 
+    let mut builder = Message::to_token("token");
     builder
-        .registration_ids(&["one", "two"])
-        .collapse_key("foo")
+        .collapse_key("chat")
         .priority(Priority::High)
-        .content_available(false)
-        .delay_while_idle(true)
-        .time_to_live(420)
-        .restricted_package_name("pkg")
-        .notification(NotificationBuilder::new().finalize())
-        .dry_run(false);
+        .time_to_live(Duration::from_secs(3600))
+        .restricted_package_name("com.example.chat")
+        .content_available(true)
+        .mutable_content(true)
+        .dry_run(true)
+        .notification(notification.finalize());
+    builder.data(&CustomData { foo: "bar", bar: true }).unwrap();
 
-    let payload = serde_json::to_string(&builder.finalize().body).unwrap();
+    let payload = serde_json::to_value(builder.finalize().unwrap()).unwrap();
 
     let expected_payload = json!({
-        "to": "token",
-        "registration_ids": ["one", "two"],
-        "collapse_key": "foo",
-        "priority": "high",
-        "content_available": false,
-        "delay_while_idle": true,
-        "time_to_live": 420,
-        "restricted_package_name": "pkg",
-        "dry_run": false,
-        "notification": {},
-    })
-    .to_string();
+        "validate_only": true,
+        "message": {
+            "data": {
+                "foo": "bar",
+                "bar": "true"
+            },
+            "notification": {
+                "title": "Hey!",
+                "body": "Do you want to catch up later?"
+            },
+            "android": {
+                "collapse_key": "chat",
+                "priority": "HIGH",
+                "ttl": "3600s",
+                "restricted_package_name": "com.example.chat",
+                "notification": {
+                    "icon": "ic_chat",
+                    "sound": "chime.caf",
+                    "tag": "chat",
+                    "click_action": "OPEN_CHAT"
+                }
+            },
+            "apns": {
+                "headers": {
+                    "apns-priority": "10"
+                },
+                "payload": {
+                    "aps": {
+                        "badge": 3,
+                        "sound": "chime.caf",
+                        "category": "OPEN_CHAT",
+                        "content-available": 1,
+                        "mutable-content": 1
+                    }
+                }
+            },
+            "token": "token"
+        }
+    });
 
     assert_eq!(expected_payload, payload);
 }
 
-#[test]
-fn should_set_registration_ids() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
-
-    assert_eq!(msg.body.registration_ids, None);
-
-    let mut builder = MessageBuilder::new("api_key", "token");
-    builder.registration_ids(&["id1"]);
-    let msg = builder.finalize();
-
-    assert_eq!(msg.body.registration_ids, Some(vec![Cow::from("id1")]));
-}
-
 #[test]
 fn should_set_collapse_key() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.collapse_key, None);
+    assert_eq!(msg.message.android, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.collapse_key("key");
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.collapse_key, Some("key"));
+    assert_eq!(msg.message.android.unwrap().collapse_key.as_deref(), Some("key"));
 }
 
 #[test]
 fn should_set_priority() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.priority, None);
+    assert_eq!(msg.message.android, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.priority(Priority::Normal);
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.priority, Some(Priority::Normal));
+    assert_eq!(msg.message.android.unwrap().priority, Some(Priority::Normal));
+    assert_eq!(
+        serde_json::to_value(msg.message.apns.unwrap().headers).unwrap(),
+        json!({"apns-priority": "5"})
+    );
 }
 
 #[test]
 fn should_set_content_available() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.content_available, None);
+    assert_eq!(msg.message.apns, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.content_available(true);
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.content_available, Some(true));
+    assert_eq!(
+        serde_json::to_value(msg.message.apns.unwrap().payload).unwrap(),
+        json!({"aps": {"content-available": 1}})
+    );
 }
 
 #[test]
 fn should_set_mutable_content() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.mutable_content, None);
+    assert_eq!(msg.message.apns, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.mutable_content(true);
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(
+        serde_json::to_value(msg.message.apns.unwrap().payload).unwrap(),
+        json!({"aps": {"mutable-content": 1}})
+    );
+}
+
+#[test]
+fn should_reject_delay_while_idle() {
+    let mut builder = Message::to_token("token");
+    builder.delay_while_idle(false);
+    assert!(builder.finalize().is_ok());
 
-    assert_eq!(msg.body.mutable_content, Some(true));
+    let mut builder = Message::to_token("token");
+    builder.delay_while_idle(true);
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
 }
 
 #[test]
-fn should_set_delay_while_idle() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+#[allow(deprecated)]
+fn should_reject_registration_ids() {
+    let ids = ["id1", "id2"];
 
-    assert_eq!(msg.body.delay_while_idle, None);
+    let builder = MessageBuilder::new_multi("api_key", &ids);
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
 
     let mut builder = MessageBuilder::new("api_key", "token");
-    builder.delay_while_idle(true);
-    let msg = builder.finalize();
+    builder.registration_ids(&ids);
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+}
+
+#[test]
+fn should_set_analytics_label() {
+    let mut builder = Message::to_token("token");
+    builder.analytics_label("spring_sale-2024.v1~%20");
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(
+        json!({"analytics_label": "spring_sale-2024.v1~%20"}),
+        serde_json::to_value(&msg.message.fcm_options).unwrap()
+    );
+}
+
+#[test]
+fn should_reject_invalid_analytics_labels() {
+    let too_long = "a".repeat(crate::MAX_ANALYTICS_LABEL_LENGTH + 1);
+
+    for label in ["", "spring sale", "sale/2024", "ünïcode", too_long.as_str()] {
+        let mut builder = Message::to_token("token");
+        builder.analytics_label(label);
+        assert!(
+            matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))),
+            "{:?}",
+            label
+        );
+    }
+
+    let mut builder = Message::to_token("token");
+    builder.android_analytics_label("spring sale");
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
 
-    assert_eq!(msg.body.delay_while_idle, Some(true));
+    let mut apns = ApnsConfigBuilder::new();
+    apns.analytics_label("spring sale");
+    let mut builder = Message::to_token("token");
+    builder.apns(apns.finalize());
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.analytics_label("spring sale");
+    let mut builder = Message::to_token("token");
+    builder.webpush(webpush.finalize().unwrap());
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
 }
 
 #[test]
 fn should_set_time_to_live() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.time_to_live, None);
+    assert_eq!(msg.message.android, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
-    builder.time_to_live(10);
-    let msg = builder.finalize();
+    let mut builder = Message::to_token("token");
+    builder.time_to_live(Duration::from_millis(10500));
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.time_to_live, Some(10));
+    assert_eq!(
+        msg.message.android.as_ref().unwrap().ttl,
+        Some(Duration::from_millis(10500))
+    );
+    assert_eq!(
+        json!({"ttl": "10.5s"}),
+        serde_json::to_value(&msg.message.android).unwrap()
+    );
+}
+
+#[test]
+fn should_reject_a_time_to_live_over_four_weeks() {
+    let mut builder = Message::to_token("token");
+    builder.time_to_live(crate::MAX_TIME_TO_LIVE);
+    assert!(builder.finalize().is_ok());
+
+    let mut builder = Message::to_token("token");
+    builder.time_to_live(crate::MAX_TIME_TO_LIVE + Duration::from_secs(1));
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
 }
 
 #[test]
 fn should_set_restricted_package_name() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.restricted_package_name, None);
+    assert_eq!(msg.message.android, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.restricted_package_name("name");
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.restricted_package_name, Some("name"));
+    assert_eq!(
+        msg.message.android.unwrap().restricted_package_name.as_deref(),
+        Some("name")
+    );
 }
 
 #[test]
 fn should_set_dry_run() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
 
-    assert_eq!(msg.body.dry_run, None);
+    assert_eq!(msg.validate_only, None);
 
-    let mut builder = MessageBuilder::new("api_key", "token");
+    let mut builder = Message::to_token("token");
     builder.dry_run(true);
-    let msg = builder.finalize();
+    let msg = builder.finalize().unwrap();
 
-    assert_eq!(msg.body.dry_run, Some(true));
+    assert_eq!(msg.validate_only, Some(true));
+}
+
+#[test]
+fn should_set_validate_only() {
+    let mut builder = Message::to_token("token");
+    builder.validate_only(true);
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.validate_only, Some(true));
+
+    let mut builder = Message::to_token("token");
+    builder.dry_run(true).validate_only(false);
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.validate_only, Some(false));
+}
+
+#[test]
+fn should_set_direct_boot_ok() {
+    let mut builder = Message::to_token("token");
+    builder.direct_boot_ok(true);
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.message.android.unwrap().direct_boot_ok, Some(true));
 }
 
 #[test]
 fn should_set_notifications() {
-    let msg = MessageBuilder::new("api_key", "token").finalize();
+    let msg = Message::to_token("token").finalize().unwrap();
+
+    assert_eq!(msg.message.notification, None);
+
+    let mut notification = NotificationBuilder::new();
+    notification.title("Hey!");
+
+    let mut builder = Message::to_token("token");
+    builder.notification(notification.finalize());
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.message.notification.unwrap().title.as_deref(), Some("Hey!"));
+}
+
+#[test]
+fn should_reject_a_non_numeric_badge() {
+    let mut notification = NotificationBuilder::new();
+    notification.badge("many");
 
-    assert_eq!(msg.body.notification, None);
+    let mut builder = Message::to_token("token");
+    builder.notification(notification.finalize());
 
-    let nm = NotificationBuilder::new().finalize();
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+}
 
+#[test]
+#[allow(deprecated)]
+fn should_set_token() {
     let mut builder = MessageBuilder::new("api_key", "token");
-    builder.notification(nm);
-    let msg = builder.finalize();
+    builder.token("other-token");
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.message.token.as_deref(), Some("other-token"));
+}
+
+#[test]
+#[allow(deprecated)]
+fn should_set_topic() {
+    let mut builder = MessageBuilder::new("api_key", "");
+    builder.topic("news");
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.message.topic.as_deref(), Some("news"));
+    assert_eq!(msg.message.token, None);
+}
+
+#[test]
+#[allow(deprecated)]
+fn should_set_condition() {
+    let mut builder = MessageBuilder::new("api_key", "");
+    builder.condition("'news' in topics");
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(msg.message.condition.as_deref(), Some("'news' in topics"));
+    assert_eq!(msg.message.token, None);
+}
+
+#[test]
+fn should_set_a_typed_condition() {
+    let builder = Message::to_condition(topic("news").and(topic("sports").or(topic("weather"))));
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(
+        msg.message.condition.as_deref(),
+        Some("'news' in topics && ('sports' in topics || 'weather' in topics)")
+    );
+    assert!(msg.validate().is_ok());
+}
+
+#[test]
+fn should_create_a_message_for_each_target() {
+    let msg = Message::to_token("token").finalize().unwrap();
+    assert_eq!(msg.message.token.as_deref(), Some("token"));
+
+    let msg = Message::to_topic("news").finalize().unwrap();
+    assert_eq!(msg.message.topic.as_deref(), Some("news"));
+    assert_eq!(msg.message.token, None);
+
+    let msg = Message::to_condition(topic("news").and(topic("sports")))
+        .finalize()
+        .unwrap();
+    assert_eq!(
+        msg.message.condition.as_deref(),
+        Some("'news' in topics && 'sports' in topics")
+    );
+    assert_eq!(msg.message.token, None);
+}
+
+fn owned_message(token: String, title: String) -> Message<'static> {
+    let mut notification = SharedNotificationBuilder::new();
+    notification.title(title);
+
+    let mut builder = Message::to_token(token);
+    builder.shared_notification(notification.finalize());
+    builder.finalize().unwrap()
+}
+
+#[test]
+fn should_build_a_static_message_from_owned_strings() {
+    let msg = owned_message("token".to_string(), format!("{} new messages", 3));
+    let copy = msg.clone();
+
+    assert_eq!(msg, copy);
+    assert_eq!(msg.message.token.as_deref(), Some("token"));
+    assert_eq!(
+        json!({"message": {"token": "token", "notification": {"title": "3 new messages"}}}),
+        serde_json::to_value(copy).unwrap()
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn should_reject_conflicting_targets() {
+    let mut builder = MessageBuilder::new("api_key", "token");
+    builder.topic("news").condition("'news' in topics");
+
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+
+    let mut builder = MessageBuilder::new("api_key", "token");
+    builder.token("token").topic("news");
+
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+
+    let mut builder = MessageBuilder::new("api_key", "token");
+    builder.topic("news");
+
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+
+    let mut builder = MessageBuilder::new("api_key", "token");
+    builder.condition("'news' in topics");
+
+    assert!(matches!(builder.finalize(), Err(crate::Error::InvalidMessage(_))));
+}
+
+#[test]
+#[allow(deprecated)]
+fn should_require_a_target() {
+    assert!(matches!(
+        MessageBuilder::new("api_key", "").finalize(),
+        Err(crate::Error::InvalidMessage(_))
+    ));
+}
+
+#[test]
+fn should_set_a_shared_notification() {
+    let mut notification = SharedNotificationBuilder::new();
+    notification
+        .title("Hey!")
+        .body("Lunch?")
+        .image("https://example.com/lunch.png");
+
+    let mut builder = Message::to_token("token");
+    builder.shared_notification(notification.finalize());
+    let payload = serde_json::to_value(builder.finalize().unwrap()).unwrap();
+
+    let expected_payload = json!({
+        "message": {
+            "notification": {
+                "title": "Hey!",
+                "body": "Lunch?",
+                "image": "https://example.com/lunch.png"
+            },
+            "token": "token"
+        }
+    });
+
+    assert_eq!(expected_payload, payload);
+}
+
+#[test]
+fn should_stack_the_legacy_notification_on_the_shared_one() {
+    let mut legacy = NotificationBuilder::new();
+    legacy.title("Legacy title").body("Legacy body").icon("ic_chat");
+
+    let mut shared = Notification::builder();
+    shared.title("Shared title").image("https://example.com/chat.png");
+
+    let mut builder = Message::to_token("token");
+    builder
+        .notification(legacy.finalize())
+        .shared_notification(shared.finalize());
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(
+        json!({
+            "title": "Shared title",
+            "body": "Legacy body",
+            "image": "https://example.com/chat.png"
+        }),
+        serde_json::to_value(&msg.message.notification).unwrap()
+    );
+    assert_eq!(
+        json!({"notification": {"icon": "ic_chat"}}),
+        serde_json::to_value(&msg.message.android).unwrap()
+    );
+}
+
+#[test]
+fn should_set_an_android_notification() {
+    let mut notification = AndroidNotificationBuilder::new();
+    notification
+        .title("Goal!")
+        .channel_id("scores")
+        .ticker("Goal for the home team")
+        .sticky(true)
+        .event_time(Utc.with_ymd_and_hms(2024, 6, 14, 19, 0, 0).unwrap())
+        .local_only(false)
+        .notification_priority(NotificationPriority::PriorityHigh)
+        .default_sound(true)
+        .vibrate_timings(vec![Duration::from_millis(500), Duration::from_secs(1)])
+        .visibility(Visibility::Public)
+        .notification_count(3)
+        .bypass_proxy_notification(true);
+
+    let mut builder = Message::to_token("token");
+    builder
+        .android_notification(notification.finalize())
+        .android_analytics_label("goal-alerts");
+    let msg = builder.finalize().unwrap();
+
+    let expected_payload = json!({
+        "notification": {
+            "title": "Goal!",
+            "channel_id": "scores",
+            "ticker": "Goal for the home team",
+            "sticky": true,
+            "event_time": "2024-06-14T19:00:00Z",
+            "local_only": false,
+            "notification_priority": "PRIORITY_HIGH",
+            "default_sound": true,
+            "vibrate_timings": ["0.5s", "1s"],
+            "visibility": "PUBLIC",
+            "notification_count": 3,
+            "bypass_proxy_notification": true
+        },
+        "fcm_options": {
+            "analytics_label": "goal-alerts"
+        }
+    });
+
+    assert_eq!(expected_payload, serde_json::to_value(&msg.message.android).unwrap());
+}
+
+#[test]
+fn should_stack_the_android_notification_on_the_legacy_one() {
+    let mut legacy = NotificationBuilder::new();
+    legacy.title("Hey!").icon("ic_legacy").color("#ff0000");
+
+    let mut android = AndroidNotification::builder();
+    android.icon("ic_android").channel_id("chat");
+
+    let mut builder = Message::to_token("token");
+    builder
+        .notification(legacy.finalize())
+        .android_notification(android.finalize());
+    let msg = builder.finalize().unwrap();
+
+    assert_eq!(
+        json!({"notification": {"icon": "ic_android", "color": "#ff0000", "channel_id": "chat"}}),
+        serde_json::to_value(&msg.message.android).unwrap()
+    );
+    assert_eq!(Some("Hey!"), msg.message.notification.unwrap().title.as_deref());
+}
+
+// Conformance with the FCM v1 REST schema. The fixtures in
+// tests/fixtures/messages are copied from the Firebase documentation, their
+// README cites the page each one comes from.
+
+fn fixture(json: &str) -> serde_json::Value {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn should_serialize_android_enums_like_the_schema() {
+    let expected = fixture(include_str!("../../tests/fixtures/messages/android_enums.json"));
+
+    assert_eq!(
+        expected["AndroidMessagePriority"],
+        serde_json::to_value([Priority::Normal, Priority::High]).unwrap()
+    );
+    assert_eq!(
+        expected["NotificationPriority"],
+        serde_json::to_value([
+            NotificationPriority::PriorityUnspecified,
+            NotificationPriority::PriorityMin,
+            NotificationPriority::PriorityLow,
+            NotificationPriority::PriorityDefault,
+            NotificationPriority::PriorityHigh,
+            NotificationPriority::PriorityMax,
+        ])
+        .unwrap()
+    );
+    assert_eq!(
+        expected["Visibility"],
+        serde_json::to_value([
+            Visibility::Unspecified,
+            Visibility::Private,
+            Visibility::Public,
+            Visibility::Secret,
+        ])
+        .unwrap()
+    );
+    assert_eq!(
+        expected["Proxy"],
+        serde_json::to_value([Proxy::Unspecified, Proxy::Allow, Proxy::Deny, Proxy::IfPriorityLowered]).unwrap()
+    );
+}
+
+#[test]
+fn should_match_the_platform_overrides_example() {
+    let mut shared = SharedNotificationBuilder::new();
+    shared
+        .title("`$FooCorp` up 1.43% on the day")
+        .body("FooCorp gained 11.80 points to close at 835.67, up 1.43% on the day.");
+
+    let mut android = AndroidNotificationBuilder::new();
+    android.icon("stock_ticker_update").color("#7e55c3");
+
+    let mut payload = ApnsPayloadBuilder::new();
+    payload.badge(42);
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.payload(payload.finalize());
+
+    let mut builder = Message::to_topic("industry-tech");
+    builder
+        .shared_notification(shared.finalize())
+        .android_notification(android.finalize())
+        .apns(apns.finalize());
+
+    assert_eq!(
+        fixture(include_str!("../../tests/fixtures/messages/platform_overrides.json")),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_match_the_platform_delivery_options_example() {
+    let mut shared = SharedNotificationBuilder::new();
+    shared
+        .title("Match update")
+        .body("Arsenal goal in added time, score is now 3-0");
+
+    let mut android = AndroidNotificationBuilder::new();
+    android.click_action("OPEN_ACTIVITY_1");
+
+    let mut payload = ApnsPayloadBuilder::new();
+    payload.category("NEW_MESSAGE_CATEGORY");
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.priority(ApnsPriority::ConservePower).payload(payload.finalize());
+
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.ttl(Duration::from_secs(86400));
+
+    let mut builder = Message::to_token("bk3RNwTe3H0:CI2k_HHwgIpoDKCIZvvDMExUdFQ3P1...");
+    builder
+        .time_to_live(Duration::from_secs(86400))
+        .shared_notification(shared.finalize())
+        .android_notification(android.finalize())
+        .apns(apns.finalize())
+        .webpush(webpush.finalize().unwrap());
+
+    assert_eq!(
+        fixture(include_str!(
+            "../../tests/fixtures/messages/platform_delivery_options.json"
+        )),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_match_the_live_activity_start_example() {
+    let mut payload = ApnsPayloadBuilder::new();
+    payload
+        .title("test title")
+        .body("test body")
+        .live_activity_event(LiveActivityEvent::Start)
+        .timestamp(Utc.timestamp_opt(1718391600, 0).unwrap());
+    payload.content_state(&json!({"demo": 1})).unwrap();
+    payload
+        .attributes("DemoAttributes", &json!({"demoAttribute": 1}))
+        .unwrap();
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.priority(ApnsPriority::Immediate)
+        .live_activity_token("LIVE_ACTIVITY_PUSH_TO_START_TOKEN")
+        .payload(payload.finalize());
+
+    let mut builder = Message::to_token("FCM_TOKEN");
+    builder.apns(apns.finalize());
+
+    assert_eq!(
+        fixture(include_str!("../../tests/fixtures/messages/live_activity_start.json")),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_match_the_webpush_link_example() {
+    let mut shared = SharedNotificationBuilder::new();
+    shared.title("Background Message Title").body("Background message body");
+
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.link("https://dummypage.com");
+
+    let mut builder = Message::to_token("bk3RNwTe3H0:CI2k_HHwgIpoDKCIZvvDMExUdFQ3P1...");
+    builder
+        .shared_notification(shared.finalize())
+        .webpush(webpush.finalize().unwrap());
+
+    assert_eq!(
+        fixture(include_str!("../../tests/fixtures/messages/webpush_link.json")),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+// Payloads setting every field at once.
+
+#[test]
+fn should_set_every_android_notification_field_of_the_schema() {
+    let mut shared = SharedNotificationBuilder::new();
+    shared.title("Match update").body("Home team scored");
+
+    let mut android = AndroidNotificationBuilder::new();
+    android
+        .title("Goal!")
+        .body("Home team leads 2-1")
+        .icon("ic_goal")
+        .color("#00ff00")
+        .sound("default")
+        .tag("match-42")
+        .click_action("OPEN_MATCH")
+        .body_loc_key("GOAL_BODY")
+        .body_loc_args(&["Home", "2-1"])
+        .title_loc_key("GOAL_TITLE")
+        .title_loc_args(&["Home"])
+        .channel_id("scores")
+        .ticker("Goal for Home")
+        .sticky(false)
+        .event_time(Utc.with_ymd_and_hms(2024, 6, 14, 19, 42, 0).unwrap())
+        .local_only(true)
+        .notification_priority(NotificationPriority::PriorityMax)
+        .default_sound(false)
+        .default_vibrate_timings(true)
+        .default_light_settings(true)
+        .light_settings(LightSettings::new(
+            Color::from_hex("#00ff00").unwrap(),
+            Duration::from_millis(500),
+            Duration::from_secs(2),
+        ))
+        .vibrate_timings(vec![Duration::from_millis(100), Duration::from_millis(200)])
+        .visibility(Visibility::Private)
+        .notification_count(2)
+        .image("https://example.com/goal.png")
+        .bypass_proxy_notification(false)
+        .proxy(Proxy::IfPriorityLowered);
+
+    let mut builder = Message::to_topic("match-42");
+    builder
+        .collapse_key("score_update")
+        .priority(Priority::High)
+        .time_to_live(Duration::from_secs(86400))
+        .restricted_package_name("com.example.scores")
+        .shared_notification(shared.finalize())
+        .android_notification(android.finalize())
+        .android_analytics_label("goal_alerts");
+
+    let payload = serde_json::to_value(builder.finalize().unwrap()).unwrap();
+
+    let mut fields: Vec<_> = payload["message"]["android"]["notification"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    let mut expected_fields: Vec<String> = serde_json::from_value(fixture(include_str!(
+        "../../tests/fixtures/messages/android_notification_fields.json"
+    )))
+    .unwrap();
+    fields.sort();
+    expected_fields.sort();
+
+    assert_eq!(expected_fields, fields);
+    assert_eq!(
+        json!({
+          "message": {
+            "notification": {
+              "title": "Match update",
+              "body": "Home team scored"
+            },
+            "android": {
+              "collapse_key": "score_update",
+              "priority": "HIGH",
+              "ttl": "86400s",
+              "restricted_package_name": "com.example.scores",
+              "notification": {
+                "title": "Goal!",
+                "body": "Home team leads 2-1",
+                "icon": "ic_goal",
+                "color": "#00ff00",
+                "sound": "default",
+                "tag": "match-42",
+                "click_action": "OPEN_MATCH",
+                "body_loc_key": "GOAL_BODY",
+                "body_loc_args": ["Home", "2-1"],
+                "title_loc_key": "GOAL_TITLE",
+                "title_loc_args": ["Home"],
+                "channel_id": "scores",
+                "ticker": "Goal for Home",
+                "sticky": false,
+                "event_time": "2024-06-14T19:42:00Z",
+                "local_only": true,
+                "notification_priority": "PRIORITY_MAX",
+                "default_sound": false,
+                "default_vibrate_timings": true,
+                "default_light_settings": true,
+                "light_settings": {
+                  "color": {
+                    "red": 0.0,
+                    "green": 1.0,
+                    "blue": 0.0
+                  },
+                  "light_on_duration": "0.5s",
+                  "light_off_duration": "2s"
+                },
+                "vibrate_timings": ["0.1s", "0.2s"],
+                "visibility": "PRIVATE",
+                "notification_count": 2,
+                "image": "https://example.com/goal.png",
+                "bypass_proxy_notification": false,
+                "proxy": "IF_PRIORITY_LOWERED"
+              },
+              "fcm_options": {
+                "analytics_label": "goal_alerts"
+              }
+            },
+            "apns": {
+              "headers": {
+                "apns-priority": "10"
+              }
+            },
+            "topic": "match-42"
+          }
+        }),
+        payload
+    );
+}
+
+#[test]
+fn should_render_a_full_legacy_notification() {
+    let mut notification = NotificationBuilder::new();
+    notification
+        .title("Hey!")
+        .body("Do you want to catch up later?")
+        .icon("ic_chat")
+        .color("#666666")
+        .sound("pling")
+        .badge("1")
+        .tag("chat")
+        .click_action("OPEN_CHAT")
+        .body_loc_key("CATCH_UP")
+        .body_loc_args(&["later"]);
+
+    let mut builder = Message::to_token("registration-token");
+    builder
+        .priority(Priority::Normal)
+        .content_available(true)
+        .dry_run(true)
+        .notification(notification.finalize());
+
+    assert_eq!(
+        json!({
+          "validate_only": true,
+          "message": {
+            "notification": {
+              "title": "Hey!",
+              "body": "Do you want to catch up later?"
+            },
+            "android": {
+              "priority": "NORMAL",
+              "notification": {
+                "icon": "ic_chat",
+                "color": "#666666",
+                "sound": "pling",
+                "tag": "chat",
+                "click_action": "OPEN_CHAT",
+                "body_loc_key": "CATCH_UP",
+                "body_loc_args": ["later"]
+              }
+            },
+            "apns": {
+              "headers": {
+                "apns-priority": "5"
+              },
+              "payload": {
+                "aps": {
+                  "alert": {
+                    "loc-key": "CATCH_UP",
+                    "loc-args": ["later"]
+                  },
+                  "badge": 1,
+                  "sound": "pling",
+                  "category": "OPEN_CHAT",
+                  "content-available": 1
+                }
+              }
+            },
+            "token": "registration-token"
+          }
+        }),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_render_a_full_apns_payload() {
+    let mut shared = SharedNotificationBuilder::new();
+    shared
+        .title("Gate changed")
+        .body("Your flight now departs from gate B12");
+
+    let mut payload = ApnsPayloadBuilder::new();
+    payload
+        .title("Gate changed")
+        .subtitle("LH 454")
+        .body("Your flight now departs from gate B12")
+        .badge(1)
+        .sound(ApnsSound::Critical {
+            name: "gate.caf".into(),
+            volume: 0.5,
+        })
+        .thread_id("flight-454")
+        .category("GATE_CHANGE")
+        .interruption_level(InterruptionLevel::TimeSensitive)
+        .relevance_score(0.75);
+    payload.custom_key("flight", &"LH 454").unwrap();
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.priority(ApnsPriority::ConservePower)
+        .expiration(Utc.with_ymd_and_hms(2024, 6, 14, 19, 0, 0).unwrap())
+        .push_type(ApnsPushType::Alert)
+        .collapse_id("gate-change")
+        .topic("com.example.flights")
+        .payload(payload.finalize())
+        .analytics_label("gate_changes")
+        .image("https://example.com/gate.png");
+
+    let mut builder = Message::to_token("registration-token");
+    builder
+        .priority(Priority::High)
+        .mutable_content(true)
+        .shared_notification(shared.finalize())
+        .apns(apns.finalize());
+
+    assert_eq!(
+        json!({
+          "message": {
+            "notification": {
+              "title": "Gate changed",
+              "body": "Your flight now departs from gate B12"
+            },
+            "android": {
+              "priority": "HIGH"
+            },
+            "apns": {
+              "headers": {
+                "apns-priority": "5",
+                "apns-expiration": "1718391600",
+                "apns-push-type": "alert",
+                "apns-collapse-id": "gate-change",
+                "apns-topic": "com.example.flights"
+              },
+              "payload": {
+                "aps": {
+                  "alert": {
+                    "title": "Gate changed",
+                    "subtitle": "LH 454",
+                    "body": "Your flight now departs from gate B12"
+                  },
+                  "badge": 1,
+                  "sound": {
+                    "critical": 1,
+                    "name": "gate.caf",
+                    "volume": 0.5
+                  },
+                  "thread-id": "flight-454",
+                  "category": "GATE_CHANGE",
+                  "mutable-content": 1,
+                  "interruption-level": "time-sensitive",
+                  "relevance-score": 0.75
+                },
+                "flight": "LH 454"
+              },
+              "fcm_options": {
+                "analytics_label": "gate_changes",
+                "image": "https://example.com/gate.png"
+              }
+            },
+            "token": "registration-token"
+          }
+        }),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_render_a_full_live_activity() {
+    let mut payload = ApnsPayloadBuilder::new();
+    payload
+        .title("Kick-off")
+        .body("Home vs Away has started")
+        .live_activity_event(LiveActivityEvent::Start)
+        .timestamp(Utc.with_ymd_and_hms(2024, 6, 14, 19, 0, 0).unwrap())
+        .dismissal_date(Utc.with_ymd_and_hms(2024, 6, 14, 22, 0, 0).unwrap());
+    payload.content_state(&json!({"home": 0, "away": 0})).unwrap();
+    payload
+        .attributes("MatchAttributes", &json!({"homeTeam": "Home", "awayTeam": "Away"}))
+        .unwrap();
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.priority(ApnsPriority::Immediate)
+        .live_activity("com.example.scores")
+        .payload(payload.finalize());
+
+    let mut builder = Message::to_token("registration-token");
+    builder.apns(apns.finalize());
+
+    assert_eq!(
+        json!({
+          "message": {
+            "apns": {
+              "headers": {
+                "apns-priority": "10",
+                "apns-push-type": "liveactivity",
+                "apns-topic": "com.example.scores.push-type.liveactivity"
+              },
+              "payload": {
+                "aps": {
+                  "alert": {
+                    "title": "Kick-off",
+                    "body": "Home vs Away has started"
+                  },
+                  "event": "start",
+                  "content-state": {
+                    "home": 0,
+                    "away": 0
+                  },
+                  "timestamp": 1718391600,
+                  "dismissal-date": 1718402400,
+                  "attributes-type": "MatchAttributes",
+                  "attributes": {
+                    "homeTeam": "Home",
+                    "awayTeam": "Away"
+                  }
+                }
+              }
+            },
+            "token": "registration-token"
+          }
+        }),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn should_render_a_full_webpush_config() {
+    let mut notification = WebpushNotificationBuilder::new();
+    notification
+        .title("New message")
+        .body("Do you want to catch up later?")
+        .icon("https://example.com/icon.png")
+        .action(WebpushNotificationAction::new("reply", "Reply"))
+        .require_interaction(true)
+        .tag("chat-42")
+        .vibrate(vec![
+            Duration::from_millis(200),
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        ]);
+
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush
+        .ttl(Duration::from_secs(3600))
+        .urgency(WebpushUrgency::High)
+        .topic("chat-42")
+        .notification(notification.finalize())
+        .link("https://example.com/chat/42")
+        .analytics_label("chat_messages");
+    webpush.data(&json!({"chat_id": "42", "origin": "web"})).unwrap();
+
+    let mut builder = Message::to_token("registration-token");
+    builder.data(&json!({"chat_id": "42"})).unwrap();
+    builder.webpush(webpush.finalize().unwrap());
+
+    assert_eq!(
+        json!({
+          "message": {
+            "data": {
+              "chat_id": "42"
+            },
+            "webpush": {
+              "headers": {
+                "TTL": "3600",
+                "Urgency": "high",
+                "Topic": "chat-42"
+              },
+              "data": {
+                "chat_id": "42",
+                "origin": "web"
+              },
+              "notification": {
+                "title": "New message",
+                "body": "Do you want to catch up later?",
+                "icon": "https://example.com/icon.png",
+                "actions": [
+                  {
+                    "action": "reply",
+                    "title": "Reply"
+                  }
+                ],
+                "requireInteraction": true,
+                "tag": "chat-42",
+                "vibrate": [200, 100, 200]
+              },
+              "fcm_options": {
+                "link": "https://example.com/chat/42",
+                "analytics_label": "chat_messages"
+              }
+            },
+            "token": "registration-token"
+          }
+        }),
+        serde_json::to_value(builder.finalize().unwrap()).unwrap()
+    );
+}
+
+// Local validation
+
+fn violated_fields(builder: MessageBuilder<FixedTarget>) -> Vec<String> {
+    match builder.finalize().unwrap().validate() {
+        Ok(()) => Vec::new(),
+        Err(error) => error.field_violations().iter().map(|v| v.field.clone()).collect(),
+    }
+}
+
+#[test]
+fn should_validate_a_valid_message() {
+    let mut notification = SharedNotificationBuilder::new();
+    notification.title("Hey!").image("https://example.com/image.png");
+
+    let mut builder = Message::to_condition("'news' in topics && ('sports' in topics || 'weather' in topics)");
+    builder
+        .shared_notification(notification.finalize())
+        .analytics_label("news");
+    builder.data(&json!({"article": "42"})).unwrap();
+
+    assert_eq!(Vec::<String>::new(), violated_fields(builder));
+}
+
+#[test]
+fn should_reject_reserved_data_keys() {
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.data(&json!({"gcm.notification.title": "Hey!"})).unwrap();
+
+    let mut builder = Message::to_token("token");
+    builder.webpush(webpush.finalize().unwrap());
+    builder
+        .data(&json!({
+            "from": "Alice",
+            "gcm": "1",
+            "google.c.a.e": "1",
+            "message_type": "chat",
+            "collapse_key": "chat",
+            "googleplex": "fine",
+        }))
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            "message.data.collapse_key",
+            "message.data.from",
+            "message.data.gcm",
+            "message.data.google.c.a.e",
+            "message.data.message_type",
+            "message.webpush.data.gcm.notification.title",
+        ],
+        violated_fields(builder)
+    );
+}
+
+#[test]
+fn should_reject_payloads_over_4096_bytes() {
+    let body = "x".repeat(crate::MAX_PAYLOAD_SIZE);
+
+    let mut notification = SharedNotificationBuilder::new();
+    notification.body(&body);
+
+    let mut builder = Message::to_token("token");
+    builder.shared_notification(notification.finalize());
+
+    let error = builder.finalize().unwrap().validate().unwrap_err();
+    assert!(matches!(error, crate::Error::InvalidFields(_)));
+    assert!(
+        error.to_string().contains("exceeds the maximum of 4096 bytes"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn should_accept_payloads_just_under_4096_bytes() {
+    // 4 bytes for the "body" key, the rest for its value.
+    let body = "x".repeat(crate::MAX_PAYLOAD_SIZE - 4);
+
+    let mut notification = SharedNotificationBuilder::new();
+    notification.body(&body);
+
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.ttl(Duration::from_secs(3600)).urgency(WebpushUrgency::High);
+
+    let mut builder = Message::to_token("token");
+    builder
+        .shared_notification(notification.finalize())
+        .collapse_key("updates")
+        .time_to_live(Duration::from_secs(3600))
+        .restricted_package_name("com.example.app")
+        .webpush(webpush.finalize().unwrap());
+
+    assert_eq!(Vec::<String>::new(), violated_fields(builder));
+}
+
+#[test]
+fn should_reject_invalid_topics_and_conditions() {
+    let builder = Message::to_topic("breaking news");
+    assert_eq!(vec!["message.topic"], violated_fields(builder));
+
+    let builder = Message::to_topic("breaking-news");
+    assert_eq!(Vec::<String>::new(), violated_fields(builder));
+
+    let builder = Message::to_topic("/topics/breaking-news");
+    let error = builder.finalize().unwrap().validate().unwrap_err();
+    assert_eq!("message.topic", error.field_violations()[0].field);
+    assert!(error.to_string().contains("without the /topics/ prefix"), "{}", error);
+
+    let builder = Message::to_condition(
+        "'a' in topics || 'b' in topics || 'c' in topics || 'd' in topics || 'e' in topics || 'f' in topics",
+    );
+    assert_eq!(vec!["message.condition"], violated_fields(builder));
+
+    let builder = Message::to_condition("'breaking news' in topics");
+    assert_eq!(vec!["message.condition"], violated_fields(builder));
+}
+
+#[test]
+fn should_reject_empty_targets() {
+    assert_eq!(vec!["message.token"], violated_fields(Message::to_token("")));
+    assert_eq!(vec!["message.topic"], violated_fields(Message::to_topic("")));
+    assert_eq!(vec!["message.topic"], violated_fields(Message::to_topic("/topics/")));
+    assert_eq!(vec!["message.condition"], violated_fields(Message::to_condition(" ")));
+}
+
+#[test]
+fn should_reject_images_other_than_https() {
+    let mut notification = SharedNotificationBuilder::new();
+    notification.image("http://example.com/image.png");
+
+    let mut android = AndroidNotificationBuilder::new();
+    android.image("image.png");
+
+    let mut apns = ApnsConfigBuilder::new();
+    apns.image("ftp://example.com/image.png");
+
+    let mut webpush_notification = WebpushNotificationBuilder::new();
+    webpush_notification.image("https://example.com/image.png");
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.notification(webpush_notification.finalize());
+
+    let mut builder = Message::to_token("token");
+    builder
+        .shared_notification(notification.finalize())
+        .android_notification(android.finalize())
+        .apns(apns.finalize())
+        .webpush(webpush.finalize().unwrap());
+
+    assert_eq!(
+        vec![
+            "message.notification.image",
+            "message.android.notification.image",
+            "message.apns.fcm_options.image",
+        ],
+        violated_fields(builder)
+    );
+}
+
+#[test]
+fn should_reject_a_webpush_time_to_live_over_four_weeks() {
+    let mut webpush = WebpushConfigBuilder::new();
+    webpush.ttl(crate::MAX_TIME_TO_LIVE + Duration::from_secs(1));
+
+    let mut builder = Message::to_token("token");
+    builder.webpush(webpush.finalize().unwrap());
 
-    assert!(msg.body.notification != None);
+    assert_eq!(vec!["message.webpush.headers.TTL"], violated_fields(builder));
 }
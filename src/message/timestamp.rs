@@ -0,0 +1,37 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serializer;
+
+/// Format a point in time like the JSON mapping of
+/// `google.protobuf.Timestamp`: RFC 3339 in UTC with a `Z` suffix and only
+/// as many fractional digits as needed.
+pub(crate) fn format(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Serialize an optional point in time as a `google.protobuf.Timestamp`.
+pub(crate) fn serialize_option<S: Serializer>(
+    timestamp: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(ref timestamp) => serializer.serialize_str(&format(timestamp)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_format_timestamps() {
+        let timestamp = Utc.with_ymd_and_hms(2014, 10, 2, 15, 1, 23).unwrap();
+
+        assert_eq!("2014-10-02T15:01:23Z", format(&timestamp));
+        assert_eq!(
+            "2014-10-02T15:01:23.045Z",
+            format(&(timestamp + chrono::Duration::milliseconds(45)))
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::client::response::{FcmError, FieldViolation};
+use crate::message::{duration, validate_analytics_label, Condition, DataPayload, Message, MAX_TIME_TO_LIVE};
+
+/// The most bytes the data and notification of a message can take, for
+/// each platform.
+pub const MAX_PAYLOAD_SIZE: usize = 4096;
+
+/// The most topics a condition can combine.
+pub const MAX_TOPICS_IN_CONDITION: usize = 5;
+
+/// Data keys FCM uses itself.
+const RESERVED_DATA_KEYS: [&str; 4] = ["from", "gcm", "message_type", "collapse_key"];
+
+/// Prefixes of data keys FCM uses itself.
+const RESERVED_DATA_KEY_PREFIXES: [&str; 2] = ["google.", "gcm."];
+
+impl<'a> Message<'a> {
+    /// Check the message against the constraints FCM documents, so that an
+    /// invalid message is caught without a round trip. `Client::send` does
+    /// this before sending.
+    ///
+    /// Fails with `FcmError::InvalidFields`, listing every violation found.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use fcm::{DataPayload, Message};
+    ///
+    /// let mut data = DataPayload::new();
+    /// data.insert("from", "Alice");
+    ///
+    /// let mut builder = Message::to_token("<registration id>");
+    /// builder.data(&data)?;
+    /// let message = builder.finalize()?;
+    ///
+    /// let error = message.validate().unwrap_err();
+    /// assert_eq!("message.data.from", error.field_violations()[0].field);
+    /// # Ok::<(), fcm::Error>(())
+    /// ```
+    pub fn validate(&self) -> Result<(), FcmError> {
+        let message = &self.message;
+        let mut violations = Vec::new();
+
+        let targets = [
+            message.token.is_some(),
+            message.topic.is_some(),
+            message.condition.is_some(),
+        ];
+
+        match targets.iter().filter(|target| **target).count() {
+            1 => (),
+            0 => violations.push(FieldViolation::new(
+                "message",
+                "a token, topic or condition is required",
+            )),
+            _ => violations.push(FieldViolation::new(
+                "message",
+                "only one of token, topic and condition can be set",
+            )),
+        }
+
+        if let Some(ref token) = message.token {
+            if token.trim().is_empty() {
+                violations.push(FieldViolation::new("message.token", "token can't be empty"));
+            }
+        }
+
+        if let Some(ref topic) = message.topic {
+            if topic.starts_with("/topics/") {
+                violations.push(FieldViolation::new(
+                    "message.topic",
+                    format!("topic {:?} must be given without the /topics/ prefix", topic),
+                ));
+            } else if let Err(description) = validate_topic_name(topic) {
+                violations.push(FieldViolation::new("message.topic", description));
+            }
+        }
+
+        if let Some(ref condition) = message.condition {
+            if condition.trim().is_empty() {
+                violations.push(FieldViolation::new("message.condition", "condition can't be empty"));
+            } else {
+                check_condition(condition, &mut violations);
+            }
+        }
+
+        // What each platform gets: the data of the message unless the
+        // platform overrides it, the shared notification and the platform's
+        // own notification or APNs payload.
+        let data_size = key_value_size(message.data.as_ref());
+        let notification_size = key_value_size(message.notification.as_ref());
+        let android_size = message.android.as_ref().map_or(data_size, |android| {
+            android
+                .data
+                .as_ref()
+                .map_or(data_size, |data| key_value_size(Some(data)))
+                + key_value_size(android.notification.as_ref())
+        });
+        let apns_size = data_size + key_value_size(message.apns.as_ref().and_then(|apns| apns.payload.as_ref()));
+        let webpush_size = message.webpush.as_ref().map_or(data_size, |webpush| {
+            webpush.data().map_or(data_size, |data| key_value_size(Some(data))) + key_value_size(webpush.notification())
+        });
+        let payload_size = notification_size + android_size.max(apns_size).max(webpush_size);
+
+        if payload_size > MAX_PAYLOAD_SIZE {
+            violations.push(FieldViolation::new(
+                "message",
+                format!(
+                    "payload of {} bytes exceeds the maximum of {} bytes",
+                    payload_size, MAX_PAYLOAD_SIZE
+                ),
+            ));
+        }
+
+        check_data("message.data", message.data.as_ref(), &mut violations);
+
+        let notification_image = message
+            .notification
+            .as_ref()
+            .and_then(|notification| notification.image.as_deref());
+        check_image("message.notification.image", notification_image, &mut violations);
+
+        let analytics_label = message
+            .fcm_options
+            .as_ref()
+            .and_then(|options| options.analytics_label.as_deref());
+        check_analytics_label("message.fcm_options.analytics_label", analytics_label, &mut violations);
+
+        if let Some(ref android) = message.android {
+            check_ttl("message.android.ttl", android.ttl, &mut violations);
+            check_data("message.android.data", android.data.as_ref(), &mut violations);
+
+            let image = android
+                .notification
+                .as_ref()
+                .and_then(|notification| notification.image.as_deref());
+            check_image("message.android.notification.image", image, &mut violations);
+
+            let analytics_label = android
+                .fcm_options
+                .as_ref()
+                .and_then(|options| options.analytics_label.as_deref());
+            check_analytics_label(
+                "message.android.fcm_options.analytics_label",
+                analytics_label,
+                &mut violations,
+            );
+        }
+
+        if let Some(ref apns) = message.apns {
+            check_image("message.apns.fcm_options.image", apns.image(), &mut violations);
+            check_analytics_label(
+                "message.apns.fcm_options.analytics_label",
+                apns.analytics_label(),
+                &mut violations,
+            );
+        }
+
+        if let Some(ref webpush) = message.webpush {
+            check_ttl("message.webpush.headers.TTL", webpush.ttl(), &mut violations);
+            check_data("message.webpush.data", webpush.data(), &mut violations);
+            check_image("message.webpush.notification.image", webpush.image(), &mut violations);
+            check_analytics_label(
+                "message.webpush.fcm_options.analytics_label",
+                webpush.analytics_label(),
+                &mut violations,
+            );
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(FcmError::InvalidFields(violations))
+        }
+    }
+}
+
+/// Check a topic name, without the `/topics/` prefix, against
+/// `[a-zA-Z0-9-_.~%]+`. The error describes what is wrong.
+pub(crate) fn validate_topic_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("topic name can't be empty".to_string());
+    }
+
+    match name.chars().find(|c| !is_name_char(*c)) {
+        Some(c) => Err(format!(
+            "topic name {:?} contains {:?}, only letters, digits and -_.~% are allowed",
+            name, c
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether FCM allows `c` in topic names and analytics labels, which both
+/// follow `[a-zA-Z0-9-_.~%]`.
+pub(crate) fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '%')
+}
+
+fn check_condition(condition: &str, violations: &mut Vec<FieldViolation>) {
+    match Condition::parse(condition) {
+        Ok(condition) => condition.check("message.condition", violations),
+        Err(description) => violations.push(FieldViolation::new("message.condition", description)),
+    }
+}
+
+fn check_data(field: &str, data: Option<&DataPayload>, violations: &mut Vec<FieldViolation>) {
+    let keys = data.into_iter().flat_map(|data| data.iter()).map(|(key, _)| key);
+
+    for key in keys {
+        let is_reserved = RESERVED_DATA_KEYS.contains(&key)
+            || RESERVED_DATA_KEY_PREFIXES.iter().any(|prefix| key.starts_with(prefix));
+
+        if is_reserved {
+            violations.push(FieldViolation::new(
+                format!("{}.{}", field, key),
+                format!("{} is a reserved key", key),
+            ));
+        }
+    }
+}
+
+fn check_ttl(field: &str, ttl: Option<Duration>, violations: &mut Vec<FieldViolation>) {
+    if let Some(ttl) = ttl.filter(|ttl| *ttl > MAX_TIME_TO_LIVE) {
+        violations.push(FieldViolation::new(
+            field,
+            format!(
+                "time to live of {} exceeds the maximum of {}",
+                duration::format(&ttl),
+                duration::format(&MAX_TIME_TO_LIVE)
+            ),
+        ));
+    }
+}
+
+fn check_image(field: &str, image: Option<&str>, violations: &mut Vec<FieldViolation>) {
+    if let Some(image) = image {
+        match Url::parse(image) {
+            Ok(ref url) if url.scheme() == "https" => (),
+            _ => violations.push(FieldViolation::new(
+                field,
+                format!("image must be an HTTPS URL, got {}", image),
+            )),
+        }
+    }
+}
+
+fn check_analytics_label(field: &str, analytics_label: Option<&str>, violations: &mut Vec<FieldViolation>) {
+    if let Some(Err(description)) = analytics_label.map(validate_analytics_label) {
+        violations.push(FieldViolation::new(field, description));
+    }
+}
+
+/// The bytes FCM counts toward `MAX_PAYLOAD_SIZE` for a part of the
+/// message: those of its keys and values, without the JSON punctuation.
+fn key_value_size<T: Serialize>(part: Option<&T>) -> usize {
+    fn size(value: &Value) -> usize {
+        match value {
+            Value::Null => 0,
+            Value::String(string) => string.len(),
+            Value::Array(values) => values.iter().map(size).sum(),
+            Value::Object(map) => map.iter().map(|(key, value)| key.len() + size(value)).sum(),
+            other => other.to_string().len(),
+        }
+    }
+
+    part.and_then(|part| serde_json::to_value(part).ok())
+        .map_or(0, |value| size(&value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_topic_names() {
+        assert!(validate_topic_name("news-2024_v1.~%20").is_ok());
+
+        for name in ["", "breaking news", "news/sports", "ñews"] {
+            assert!(validate_topic_name(name).is_err(), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Serialize, Serializer};
+
+use crate::client::response::FcmError;
+use crate::message::{DataPayload, ToFcmData};
+
+/// How urgent a message is to the user agent, sent as `Urgency`. Low
+/// urgency messages may be held back to save battery.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebpushUrgency {
+    VeryLow,
+    Low,
+    Normal,
+    High,
+}
+
+/// The direction the text of a notification is shown in.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum WebpushDirection {
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+/// A button shown with a web notification.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct WebpushNotificationAction<'a> {
+    /// The id of the action, given to the service worker when clicked.
+    action: Cow<'a, str>,
+
+    /// The label of the button.
+    title: Cow<'a, str>,
+
+    /// The URL of an icon shown on the button.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Cow<'a, str>>,
+}
+
+impl<'a> WebpushNotificationAction<'a> {
+    /// A button with the given action id and label.
+    pub fn new<A: Into<Cow<'a, str>>, T: Into<Cow<'a, str>>>(action: A, title: T) -> WebpushNotificationAction<'a> {
+        WebpushNotificationAction {
+            action: action.into(),
+            title: title.into(),
+            icon: None,
+        }
+    }
+
+    /// Show the given icon on the button.
+    pub fn icon<S: Into<Cow<'a, str>>>(mut self, icon: S) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+}
+
+/// A notification shown through the Web Notification API. Use the
+/// corresponding `WebpushNotificationBuilder` to get an instance.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebpushNotification<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actions: Option<Vec<WebpushNotificationAction<'a>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    badge: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dir: Option<WebpushDirection>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lang: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    renotify: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    require_interaction: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    silent: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_epoch_millis")]
+    timestamp: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_millis_seq")]
+    vibrate: Option<Vec<Duration>>,
+}
+
+/// The Web Notification API takes timestamps in milliseconds since the epoch.
+fn serialize_epoch_millis<S: Serializer>(timestamp: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serializer.serialize_i64(timestamp.timestamp_millis()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The Web Notification API takes vibration patterns in milliseconds.
+fn serialize_millis_seq<S: Serializer>(durations: &Option<Vec<Duration>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match durations {
+        Some(durations) => serializer.collect_seq(durations.iter().map(Duration::as_millis)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// TTL is given in seconds.
+fn serialize_ttl<S: Serializer>(ttl: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match ttl {
+        Some(ttl) => serializer.serialize_str(&ttl.as_secs().to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// A builder to get a `WebpushNotification` instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{WebpushNotificationAction, WebpushNotificationBuilder};
+///
+/// let mut builder = WebpushNotificationBuilder::new();
+/// builder
+///     .title("New message")
+///     .body("Do you want to catch up later?")
+///     .icon("https://example.com/icon.png")
+///     .action(WebpushNotificationAction::new("reply", "Reply"))
+///     .require_interaction(true);
+/// let notification = builder.finalize();
+/// ```
+#[derive(Default, Debug)]
+pub struct WebpushNotificationBuilder<'a> {
+    notification: WebpushNotification<'a>,
+}
+
+impl<'a> WebpushNotificationBuilder<'a> {
+    /// Get a new `WebpushNotificationBuilder` instance.
+    pub fn new() -> WebpushNotificationBuilder<'a> {
+        Self::default()
+    }
+
+    /// Set the title of the notification.
+    pub fn title<S: Into<Cow<'a, str>>>(&mut self, title: S) -> &mut Self {
+        self.notification.title = Some(title.into());
+        self
+    }
+
+    /// Set the body of the notification.
+    pub fn body<S: Into<Cow<'a, str>>>(&mut self, body: S) -> &mut Self {
+        self.notification.body = Some(body.into());
+        self
+    }
+
+    /// The URL of the icon of the notification.
+    pub fn icon<S: Into<Cow<'a, str>>>(&mut self, icon: S) -> &mut Self {
+        self.notification.icon = Some(icon.into());
+        self
+    }
+
+    /// The URL of an image shown in the notification.
+    pub fn image<S: Into<Cow<'a, str>>>(&mut self, image: S) -> &mut Self {
+        self.notification.image = Some(image.into());
+        self
+    }
+
+    /// Add a button to the notification. Browsers show a limited number of
+    /// them.
+    pub fn action(&mut self, action: WebpushNotificationAction<'a>) -> &mut Self {
+        self.notification.actions.get_or_insert_with(Vec::new).push(action);
+        self
+    }
+
+    /// The URL of a small monochrome image representing the notification
+    /// when there is no room for the icon.
+    pub fn badge<S: Into<Cow<'a, str>>>(&mut self, badge: S) -> &mut Self {
+        self.notification.badge = Some(badge.into());
+        self
+    }
+
+    /// The direction the text is shown in.
+    pub fn dir(&mut self, dir: WebpushDirection) -> &mut Self {
+        self.notification.dir = Some(dir);
+        self
+    }
+
+    /// The language of the notification, as a BCP 47 language tag.
+    pub fn lang<S: Into<Cow<'a, str>>>(&mut self, lang: S) -> &mut Self {
+        self.notification.lang = Some(lang.into());
+        self
+    }
+
+    /// When `true`, the user is notified again when the notification
+    /// replaces one with the same tag.
+    pub fn renotify(&mut self, renotify: bool) -> &mut Self {
+        self.notification.renotify = Some(renotify);
+        self
+    }
+
+    /// When `true`, the notification stays until the user clicks or
+    /// dismisses it.
+    pub fn require_interaction(&mut self, require_interaction: bool) -> &mut Self {
+        self.notification.require_interaction = Some(require_interaction);
+        self
+    }
+
+    /// When `true`, no sound or vibration is played.
+    pub fn silent(&mut self, silent: bool) -> &mut Self {
+        self.notification.silent = Some(silent);
+        self
+    }
+
+    /// Notifications with the same tag replace each other.
+    pub fn tag<S: Into<Cow<'a, str>>>(&mut self, tag: S) -> &mut Self {
+        self.notification.tag = Some(tag.into());
+        self
+    }
+
+    /// When the event the notification is about happened.
+    pub fn timestamp(&mut self, timestamp: DateTime<Utc>) -> &mut Self {
+        self.notification.timestamp = Some(timestamp);
+        self
+    }
+
+    /// The vibration pattern, alternating vibrations and pauses.
+    pub fn vibrate(&mut self, vibrate: Vec<Duration>) -> &mut Self {
+        self.notification.vibrate = Some(vibrate);
+        self
+    }
+
+    /// Complete the build and get a `WebpushNotification` instance.
+    pub fn finalize(self) -> WebpushNotification<'a> {
+        self.notification
+    }
+}
+
+/// The Web Push protocol headers of a message.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct WebpushHeaders<'a> {
+    #[serde(
+        rename = "TTL",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_ttl"
+    )]
+    ttl: Option<Duration>,
+
+    #[serde(rename = "Urgency", skip_serializing_if = "Option::is_none")]
+    urgency: Option<WebpushUrgency>,
+
+    #[serde(rename = "Topic", skip_serializing_if = "Option::is_none")]
+    topic: Option<Cow<'a, str>>,
+}
+
+/// Options for features provided by the FCM SDK for Web.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct WebpushFcmOptions<'a> {
+    /// The page opened when the notification is clicked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link: Option<Cow<'a, str>>,
+
+    /// The label associated with the message's analytics data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analytics_label: Option<Cow<'a, str>>,
+}
+
+/// The Web Push specific settings of a message. Use the corresponding
+/// `WebpushConfigBuilder` to get an instance.
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
+pub struct WebpushConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<WebpushHeaders<'a>>,
+
+    /// Arbitrary key/value payload, overriding the data of the message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<DataPayload>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notification: Option<WebpushNotification<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fcm_options: Option<WebpushFcmOptions<'a>>,
+}
+
+impl<'a> WebpushConfig<'a> {
+    /// The label associated with the analytics data of the message on the
+    /// web.
+    pub(crate) fn analytics_label(&self) -> Option<&str> {
+        self.fcm_options
+            .as_ref()
+            .and_then(|options| options.analytics_label.as_deref())
+    }
+
+    /// How long the push service keeps the message.
+    pub(crate) fn ttl(&self) -> Option<Duration> {
+        self.headers.as_ref().and_then(|headers| headers.ttl)
+    }
+
+    /// The data overriding the one of the message on the web.
+    pub(crate) fn data(&self) -> Option<&DataPayload> {
+        self.data.as_ref()
+    }
+
+    /// The notification shown on the web.
+    pub(crate) fn notification(&self) -> Option<&WebpushNotification<'a>> {
+        self.notification.as_ref()
+    }
+
+    /// The URL of the image shown in the notification.
+    pub(crate) fn image(&self) -> Option<&str> {
+        self.notification
+            .as_ref()
+            .and_then(|notification| notification.image.as_deref())
+    }
+}
+
+/// A builder to get a `WebpushConfig` instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use fcm::{Message, WebpushConfigBuilder, WebpushNotificationBuilder, WebpushUrgency};
+/// use std::time::Duration;
+///
+/// let mut notification = WebpushNotificationBuilder::new();
+/// notification.title("New message").body("Do you want to catch up later?");
+///
+/// let mut webpush = WebpushConfigBuilder::new();
+/// webpush
+///     .ttl(Duration::from_secs(3600))
+///     .urgency(WebpushUrgency::High)
+///     .notification(notification.finalize())
+///     .link("https://example.com/chat");
+///
+/// let mut message = Message::to_token("<registration id>");
+/// message.webpush(webpush.finalize()?);
+/// # Ok::<(), fcm::Error>(())
+/// ```
+#[derive(Default, Debug)]
+pub struct WebpushConfigBuilder<'a> {
+    headers: WebpushHeaders<'a>,
+    data: Option<DataPayload>,
+    notification: Option<WebpushNotification<'a>>,
+    fcm_options: WebpushFcmOptions<'a>,
+}
+
+impl<'a> WebpushConfigBuilder<'a> {
+    /// Get a new `WebpushConfigBuilder` instance.
+    pub fn new() -> WebpushConfigBuilder<'a> {
+        Self::default()
+    }
+
+    /// How long the push service keeps the message when the browser is
+    /// offline. Only whole seconds are sent.
+    pub fn ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.headers.ttl = Some(ttl);
+        self
+    }
+
+    /// How urgent the message is.
+    pub fn urgency(&mut self, urgency: WebpushUrgency) -> &mut Self {
+        self.headers.urgency = Some(urgency);
+        self
+    }
+
+    /// Messages with the same topic replace each other while waiting for
+    /// delivery.
+    pub fn topic<S: Into<Cow<'a, str>>>(&mut self, topic: S) -> &mut Self {
+        self.headers.topic = Some(topic.into());
+        self
+    }
+
+    /// Use this to add custom key-value pairs for web clients, see
+    /// `ToFcmData` for what can be given.
+    pub fn data(&mut self, data: &dyn ToFcmData) -> Result<&mut Self, FcmError> {
+        self.data = Some(data.to_fcm_data()?);
+        Ok(self)
+    }
+
+    /// The notification shown by the browser.
+    pub fn notification(&mut self, notification: WebpushNotification<'a>) -> &mut Self {
+        self.notification = Some(notification);
+        self
+    }
+
+    /// The page opened when the notification is clicked. Must be an HTTPS
+    /// URL.
+    pub fn link<S: Into<Cow<'a, str>>>(&mut self, link: S) -> &mut Self {
+        self.fcm_options.link = Some(link.into());
+        self
+    }
+
+    /// The label associated with the analytics data of the message on the
+    /// web.
+    pub fn analytics_label<S: Into<Cow<'a, str>>>(&mut self, analytics_label: S) -> &mut Self {
+        self.fcm_options.analytics_label = Some(analytics_label.into());
+        self
+    }
+
+    /// Complete the build and get a `WebpushConfig` instance. Fails if the
+    /// link is not an HTTPS URL.
+    pub fn finalize(self) -> Result<WebpushConfig<'a>, FcmError> {
+        if let Some(ref link) = self.fcm_options.link {
+            match Url::parse(link) {
+                Ok(ref url) if url.scheme() == "https" => (),
+                _ => {
+                    return Err(FcmError::InvalidMessage(format!(
+                        "webpush link must be an HTTPS URL, got {}",
+                        link
+                    )))
+                }
+            }
+        }
+
+        Ok(WebpushConfig {
+            headers: Some(self.headers).filter(|headers| *headers != WebpushHeaders::default()),
+            data: self.data,
+            notification: self.notification,
+            fcm_options: Some(self.fcm_options).filter(|options| *options != WebpushFcmOptions::default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_a_full_notification() {
+        let mut builder = WebpushNotificationBuilder::new();
+        builder
+            .title("New message")
+            .body("Do you want to catch up later?")
+            .icon("https://example.com/icon.png")
+            .image("https://example.com/image.png")
+            .action(WebpushNotificationAction::new("reply", "Reply").icon("https://example.com/reply.png"))
+            .action(WebpushNotificationAction::new("dismiss", "Dismiss"))
+            .badge("https://example.com/badge.png")
+            .dir(WebpushDirection::Ltr)
+            .lang("en-US")
+            .renotify(true)
+            .require_interaction(true)
+            .silent(false)
+            .tag("chat")
+            .timestamp(Utc.with_ymd_and_hms(2024, 6, 14, 19, 0, 0).unwrap())
+            .vibrate(vec![Duration::from_millis(200), Duration::from_millis(100)]);
+
+        assert_eq!(
+            json!({
+                "title": "New message",
+                "body": "Do you want to catch up later?",
+                "icon": "https://example.com/icon.png",
+                "image": "https://example.com/image.png",
+                "actions": [
+                    {"action": "reply", "title": "Reply", "icon": "https://example.com/reply.png"},
+                    {"action": "dismiss", "title": "Dismiss"}
+                ],
+                "badge": "https://example.com/badge.png",
+                "dir": "ltr",
+                "lang": "en-US",
+                "renotify": true,
+                "requireInteraction": true,
+                "silent": false,
+                "tag": "chat",
+                "timestamp": 1718391600000i64,
+                "vibrate": [200, 100]
+            }),
+            serde_json::to_value(builder.finalize()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_serialize_headers() {
+        let mut builder = WebpushConfigBuilder::new();
+        builder
+            .ttl(Duration::from_millis(3_600_500))
+            .urgency(WebpushUrgency::VeryLow)
+            .topic("chat");
+
+        assert_eq!(
+            json!({"headers": {"TTL": "3600", "Urgency": "very-low", "Topic": "chat"}}),
+            serde_json::to_value(builder.finalize().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reject_links_other_than_https() {
+        for link in ["http://example.com/chat", "/chat", "example.com"] {
+            let mut builder = WebpushConfigBuilder::new();
+            builder.link(link);
+
+            assert!(
+                matches!(builder.finalize(), Err(FcmError::InvalidMessage(_))),
+                "{}",
+                link
+            );
+        }
+    }
+}
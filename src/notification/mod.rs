@@ -7,43 +7,43 @@ mod tests;
 /// This struct represents a FCM notification. Use the
 /// corresponding `NotificationBuilder` to get an instance. You can then use
 /// this notification instance when sending a FCM message.
-#[derive(Serialize, Debug, PartialEq)]
+#[derive(Serialize, Debug, PartialEq, Clone)]
 pub struct NotificationV1<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    badge: Option<&'a str>,
+    pub(crate) badge: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    body: Option<&'a str>,
+    pub(crate) body: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    body_loc_args: Option<Vec<Cow<'a, str>>>,
+    pub(crate) body_loc_args: Option<Vec<Cow<'a, str>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    body_loc_key: Option<&'a str>,
+    pub(crate) body_loc_key: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    click_action: Option<&'a str>,
+    pub(crate) click_action: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    color: Option<&'a str>,
+    pub(crate) color: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    icon: Option<&'a str>,
+    pub(crate) icon: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    sound: Option<&'a str>,
+    pub(crate) sound: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    tag: Option<&'a str>,
+    pub(crate) tag: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    title: Option<&'a str>,
+    pub(crate) title: Option<Cow<'a, str>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    title_loc_args: Option<Vec<Cow<'a, str>>>,
+    pub(crate) title_loc_args: Option<Vec<Cow<'a, str>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    title_loc_key: Option<&'a str>,
+    pub(crate) title_loc_key: Option<Cow<'a, str>>,
 }
 
 /// A builder to get a `Notification` instance.
@@ -60,17 +60,17 @@ pub struct NotificationV1<'a> {
 /// ```
 #[derive(Default)]
 pub struct NotificationBuilder<'a> {
-    title: Option<&'a str>,
-    body: Option<&'a str>,
-    icon: Option<&'a str>,
-    sound: Option<&'a str>,
-    badge: Option<&'a str>,
-    tag: Option<&'a str>,
-    color: Option<&'a str>,
-    click_action: Option<&'a str>,
-    body_loc_key: Option<&'a str>,
+    title: Option<Cow<'a, str>>,
+    body: Option<Cow<'a, str>>,
+    icon: Option<Cow<'a, str>>,
+    sound: Option<Cow<'a, str>>,
+    badge: Option<Cow<'a, str>>,
+    tag: Option<Cow<'a, str>>,
+    color: Option<Cow<'a, str>>,
+    click_action: Option<Cow<'a, str>>,
+    body_loc_key: Option<Cow<'a, str>>,
     body_loc_args: Option<Vec<Cow<'a, str>>>,
-    title_loc_key: Option<&'a str>,
+    title_loc_key: Option<Cow<'a, str>>,
     title_loc_args: Option<Vec<Cow<'a, str>>>,
 }
 
@@ -81,85 +81,85 @@ impl<'a> NotificationBuilder<'a> {
     }
 
     // Set the title of the notification
-    pub fn title(&mut self, title: &'a str) -> &mut Self {
-        self.title = Some(title);
+    pub fn title<S: Into<Cow<'a, str>>>(&mut self, title: S) -> &mut Self {
+        self.title = Some(title.into());
         self
     }
 
     /// Set the body of the notification
-    pub fn body(&mut self, body: &'a str) -> &mut Self {
-        self.body = Some(body);
+    pub fn body<S: Into<Cow<'a, str>>>(&mut self, body: S) -> &mut Self {
+        self.body = Some(body.into());
         self
     }
 
     /// Set the notification icon.
-    pub fn icon(&mut self, icon: &'a str) -> &mut Self {
-        self.icon = Some(icon);
+    pub fn icon<S: Into<Cow<'a, str>>>(&mut self, icon: S) -> &mut Self {
+        self.icon = Some(icon.into());
         self
     }
 
     /// Set the sound to be played
-    pub fn sound(&mut self, sound: &'a str) -> &mut Self {
-        self.sound = Some(sound);
+    pub fn sound<S: Into<Cow<'a, str>>>(&mut self, sound: S) -> &mut Self {
+        self.sound = Some(sound.into());
         self
     }
 
     /// Set the badge for iOS notifications
-    pub fn badge(&mut self, badge: &'a str) -> &mut Self {
-        self.badge = Some(badge);
+    pub fn badge<S: Into<Cow<'a, str>>>(&mut self, badge: S) -> &mut Self {
+        self.badge = Some(badge.into());
         self
     }
 
     /// Tagging a notification allows you to replace existing notifications
     /// with the same tag with this new notification
-    pub fn tag(&mut self, tag: &'a str) -> &mut Self {
-        self.tag = Some(tag);
+    pub fn tag<S: Into<Cow<'a, str>>>(&mut self, tag: S) -> &mut Self {
+        self.tag = Some(tag.into());
         self
     }
 
     /// The color of the icon, in #rrggbb format
-    pub fn color(&mut self, color: &'a str) -> &mut Self {
-        self.color = Some(color);
+    pub fn color<S: Into<Cow<'a, str>>>(&mut self, color: S) -> &mut Self {
+        self.color = Some(color.into());
         self
     }
 
     /// What happens when the user clicks on the notification. Refer to
     /// https://developers.google.com/cloud-messaging/http-server-ref#table2 for
     /// details.
-    pub fn click_action(&mut self, click_action: &'a str) -> &mut Self {
-        self.click_action = Some(click_action);
+    pub fn click_action<S: Into<Cow<'a, str>>>(&mut self, click_action: S) -> &mut Self {
+        self.click_action = Some(click_action.into());
         self
     }
 
     /// Set the body key string for localization
-    pub fn body_loc_key(&mut self, body_loc_key: &'a str) -> &mut Self {
-        self.body_loc_key = Some(body_loc_key);
+    pub fn body_loc_key<S: Into<Cow<'a, str>>>(&mut self, body_loc_key: S) -> &mut Self {
+        self.body_loc_key = Some(body_loc_key.into());
         self
     }
 
     /// String value to replace format specifiers in the body string.
-    pub fn body_loc_args<S>(&mut self, body_loc_args: &'a [S]) -> &mut Self
+    pub fn body_loc_args<S>(&mut self, body_loc_args: &[S]) -> &mut Self
     where
-        S: Into<Cow<'a, str>> + AsRef<str>,
+        S: Into<Cow<'a, str>> + Clone,
     {
-        let converted = body_loc_args.iter().map(|a| a.as_ref().into()).collect();
+        let converted = body_loc_args.iter().cloned().map(Into::into).collect();
 
         self.body_loc_args = Some(converted);
         self
     }
 
     /// Set the title key string for localization
-    pub fn title_loc_key(&mut self, title_loc_key: &'a str) -> &mut Self {
-        self.title_loc_key = Some(title_loc_key);
+    pub fn title_loc_key<S: Into<Cow<'a, str>>>(&mut self, title_loc_key: S) -> &mut Self {
+        self.title_loc_key = Some(title_loc_key.into());
         self
     }
 
     /// String value to replace format specifiers in the title string.
-    pub fn title_loc_args<S>(&mut self, title_loc_args: &'a [S]) -> &mut Self
+    pub fn title_loc_args<S>(&mut self, title_loc_args: &[S]) -> &mut Self
     where
-        S: Into<Cow<'a, str>> + AsRef<str>,
+        S: Into<Cow<'a, str>> + Clone,
     {
-        let converted = title_loc_args.iter().map(|a| a.as_ref().into()).collect();
+        let converted = title_loc_args.iter().cloned().map(Into::into).collect();
 
         self.title_loc_args = Some(converted);
         self
@@ -51,7 +51,7 @@ fn should_set_notification_title() {
     builder.title("title");
     let nm = builder.finalize();
 
-    assert_eq!(nm.title, Some("title"));
+    assert_eq!(nm.title.as_deref(), Some("title"));
 }
 
 #[test]
@@ -64,7 +64,7 @@ fn should_set_notification_body() {
     builder.body("body");
     let nm = builder.finalize();
 
-    assert_eq!(nm.body, Some("body"));
+    assert_eq!(nm.body.as_deref(), Some("body"));
 }
 
 #[test]
@@ -73,7 +73,7 @@ fn should_set_notification_icon() {
     builder.icon("newicon");
     let nm = builder.finalize();
 
-    assert_eq!(nm.icon, Some("newicon"));
+    assert_eq!(nm.icon.as_deref(), Some("newicon"));
 }
 
 #[test]
@@ -86,7 +86,7 @@ fn should_set_notification_sound() {
     builder.sound("sound.wav");
     let nm = builder.finalize();
 
-    assert_eq!(nm.sound, Some("sound.wav"));
+    assert_eq!(nm.sound.as_deref(), Some("sound.wav"));
 }
 
 #[test]
@@ -99,7 +99,7 @@ fn should_set_notification_badge() {
     builder.badge("1");
     let nm = builder.finalize();
 
-    assert_eq!(nm.badge, Some("1"));
+    assert_eq!(nm.badge.as_deref(), Some("1"));
 }
 
 #[test]
@@ -112,7 +112,7 @@ fn should_set_notification_tag() {
     builder.tag("tag");
     let nm = builder.finalize();
 
-    assert_eq!(nm.tag, Some("tag"));
+    assert_eq!(nm.tag.as_deref(), Some("tag"));
 }
 
 #[test]
@@ -125,7 +125,7 @@ fn should_set_notification_color() {
     builder.color("color");
     let nm = builder.finalize();
 
-    assert_eq!(nm.color, Some("color"));
+    assert_eq!(nm.color.as_deref(), Some("color"));
 }
 
 #[test]
@@ -138,7 +138,7 @@ fn should_set_notification_click_action() {
     builder.click_action("action");
     let nm = builder.finalize();
 
-    assert_eq!(nm.click_action, Some("action"));
+    assert_eq!(nm.click_action.as_deref(), Some("action"));
 }
 
 #[test]
@@ -151,7 +151,7 @@ fn should_set_notification_body_loc_key() {
     builder.body_loc_key("key");
     let nm = builder.finalize();
 
-    assert_eq!(nm.body_loc_key, Some("key"));
+    assert_eq!(nm.body_loc_key.as_deref(), Some("key"));
 }
 
 #[test]
@@ -177,7 +177,7 @@ fn should_set_notification_title_loc_key() {
     builder.title_loc_key("key");
     let nm = builder.finalize();
 
-    assert_eq!(nm.title_loc_key, Some("key"));
+    assert_eq!(nm.title_loc_key.as_deref(), Some("key"));
 }
 
 #[test]